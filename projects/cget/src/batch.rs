@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
+use tokio::task::JoinSet;
+
+use cget::{Downloader, error::ErrorKind, filename::sanitize_filename};
+
+use crate::progress::{self, Output};
+
+/// # Batch Entry
+/// A line of the input file of the batch mode: a url that is optionally
+/// followed by the name of the output file.
+///
+/// ```text
+/// # comments and empty lines are ignored
+/// https://example.com/releases/app-1.0.tar.gz
+/// https://example.com/download?id=42 report.pdf
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BatchEntry {
+    pub(crate) url: String,
+    pub(crate) output_name: Option<String>,
+}
+
+/// Parses the content of an input file into batch entries. The output names
+/// are sanitised like the names suggested by a server, so that they stay in
+/// the destination directory.
+pub(crate) fn parse_entries(content: &str) -> Vec<BatchEntry> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let url = parts.next()?.to_owned();
+            let output_name = parts.next().and_then(sanitize_filename);
+            Some(BatchEntry { url, output_name })
+        })
+        .collect()
+}
+
+/// Result of a single download of the batch
+#[derive(Debug)]
+pub(crate) struct BatchResult {
+    pub(crate) url: String,
+    pub(crate) result: Result<u64, (ErrorKind, String)>,
+}
+
+/// # Run Batch
+/// Downloads all entries at the same time. The downloaders are expected to
+/// share a connection limit so that the number of simultaneous connections
+/// across all files stays bounded.
+///
+/// Every entry is turned into a downloader by `build`, which returns an error
+/// for entries that can not be downloaded (e.g. an invalid url).
+pub(crate) async fn run(
+    entries: Vec<BatchEntry>,
+    dest: &str,
+    progress: Arc<MultiProgress>,
+    output: Output,
+    build: impl Fn(&BatchEntry) -> Result<Downloader, String>,
+) -> Vec<BatchResult> {
+    let overall = progress.add(ProgressBar::new(entries.len() as u64));
+    overall.set_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40.green/white} {pos}/{len} files")
+            .unwrap(),
+    );
+
+    let mut tasks = JoinSet::new();
+    let mut results = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        match build(entry) {
+            Ok(downloader) => {
+                let dest = dest.to_owned();
+                let overall = overall.clone();
+                let progress = Arc::clone(&progress);
+                tasks.spawn(async move {
+                    let result = progress::download(downloader, &dest, progress, output).await;
+                    overall.inc(1);
+                    let result = result.map_err(|e| (ErrorKind::of(e.as_ref()), e.to_string()));
+                    (index, result)
+                });
+            }
+            Err(e) => {
+                overall.inc(1);
+                results.push((index, Err((ErrorKind::Other, e))));
+            }
+        }
+    }
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(result) => results.push(result),
+            Err(e) => progress.suspend(|| output.println(format!("⛔ Task join error: {e}"))),
+        }
+    }
+    overall.finish();
+
+    // keep the order of the input file
+    results.sort_by_key(|(index, _)| *index);
+    results
+        .into_iter()
+        .map(|(index, result)| BatchResult {
+            url: entries[index].url.clone(),
+            result,
+        })
+        .collect()
+}
+
+/// width of the status column of the summary
+const STATUS_WIDTH: usize = 6;
+
+/// Prints a table with the status of every download of the batch.
+pub(crate) fn print_summary(results: &[BatchResult]) {
+    println!("+{}+", "-".repeat(78));
+    println!(
+        "| {:STATUS_WIDTH$} | {:>12} | {:50} |",
+        "Status", "Downloaded", "Url"
+    );
+    println!("+{}+", "-".repeat(78));
+    for result in results {
+        let (status, bytes) = match &result.result {
+            Ok(bytes) => ("✅", HumanBytes(*bytes).to_string()),
+            Err(_) => ("⛔", "-".to_owned()),
+        };
+        // the status emoji takes two columns of the terminal
+        let width = STATUS_WIDTH - 1;
+        println!("| {status:width$} | {bytes:>12} | {:50} |", result.url);
+    }
+    println!("+{}+", "-".repeat(78));
+
+    let succeeded = results.iter().filter(|r| r.result.is_ok()).count();
+    let bytes: u64 = results.iter().filter_map(|r| r.result.as_ref().ok()).sum();
+    println!(
+        "{succeeded} succeeded, {} failed, {} downloaded",
+        results.len() - succeeded,
+        HumanBytes(bytes)
+    );
+    for result in results {
+        if let Err((_, e)) = &result.result {
+            println!("⛔ {}: {e}", result.url);
+        }
+    }
+}
+
+/// returns the exit code of the batch, which is the one of the first failed
+/// download
+pub(crate) fn exit_code(results: &[BatchResult]) -> Option<i32> {
+    results
+        .iter()
+        .find_map(|r| r.result.as_ref().err())
+        .map(|(kind, _)| kind.exit_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entries() {
+        let entries = parse_entries(
+            "# releases\n\nhttps://example.com/a.tar.gz\n  https://example.com/b?id=1  b.zip \n",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                BatchEntry {
+                    url: "https://example.com/a.tar.gz".to_owned(),
+                    output_name: None,
+                },
+                BatchEntry {
+                    url: "https://example.com/b?id=1".to_owned(),
+                    output_name: Some("b.zip".to_owned()),
+                },
+            ]
+        );
+
+        let entries = parse_entries(
+            "https://example.com/a ../../etc/x
+https://example.com/b /tmp/..
+",
+        );
+        assert_eq!(entries[0].output_name.as_deref(), Some("x"));
+        assert_eq!(entries[1].output_name, None);
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    str::FromStr,
+};
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, digest::DynDigest};
+
+use crate::error::VerificationError;
+
+/// # Hash Algorithm
+/// Algorithms that can be used to verify a downloaded file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Sha256,
+    Sha1,
+    Md5,
+}
+
+impl Algorithm {
+    pub(crate) fn hasher(&self) -> Box<dyn DynDigest + Send + Sync> {
+        match self {
+            Self::Sha256 => Box::new(Sha256::new()),
+            Self::Sha1 => Box::new(Sha1::new()),
+            Self::Md5 => Box::new(Md5::new()),
+        }
+    }
+
+    /// length of a hex encoded digest of the algorithm
+    fn hex_len(&self) -> usize {
+        match self {
+            Self::Sha256 => 64,
+            Self::Sha1 => 40,
+            Self::Md5 => 32,
+        }
+    }
+
+    /// guesses the algorithm from the length of a hex encoded digest
+    fn from_hex_len(len: usize) -> Option<Self> {
+        match len {
+            64 => Some(Self::Sha256),
+            40 => Some(Self::Sha1),
+            32 => Some(Self::Md5),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "sha256" => Ok(Self::Sha256),
+            "sha1" => Ok(Self::Sha1),
+            "md5" => Ok(Self::Md5),
+            _ => Err(format!("unsupported checksum algorithm: {s}")),
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sha256 => write!(f, "sha256"),
+            Self::Sha1 => write!(f, "sha1"),
+            Self::Md5 => write!(f, "md5"),
+        }
+    }
+}
+
+/// # Checksum
+/// An expected digest of a file, written as `<algorithm>:<hex>`, for example
+/// `sha256:9f86d08...`. When the algorithm is omitted, it is guessed from the
+/// length of the digest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    pub digest: String,
+}
+
+impl FromStr for Checksum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (algorithm, digest) = match s.split_once(':') {
+            Some((algorithm, digest)) => (algorithm.parse()?, digest),
+            None => (
+                Algorithm::from_hex_len(s.len())
+                    .ok_or_else(|| format!("unable to guess the algorithm of {s:?}"))?,
+                s,
+            ),
+        };
+        if hex::decode(digest).is_err() {
+            return Err(format!("invalid hex digest: {digest:?}"));
+        }
+        if digest.len() != algorithm.hex_len() {
+            return Err(format!(
+                "a {algorithm} digest has {} hex digits, got {}",
+                algorithm.hex_len(),
+                digest.len()
+            ));
+        }
+        Ok(Self {
+            algorithm,
+            digest: digest.to_ascii_lowercase(),
+        })
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.digest)
+    }
+}
+
+impl Checksum {
+    /// # Find checksum in a checksum file
+    /// Finds the entry of the given file name in a checksum file such as
+    /// `SHA256SUMS`. Both the GNU format (`<hex>  <name>` or `<hex> *<name>`)
+    /// and the BSD format (`SHA256 (<name>) = <hex>`) are supported.
+    pub fn from_sums(content: &str, filename: &str) -> Option<Self> {
+        content.lines().find_map(|line| {
+            let line = line.trim();
+            if let Some((algorithm, rest)) = line.split_once(" (") {
+                let (name, digest) = rest.split_once(") = ")?;
+                return match name == filename {
+                    true => format!("{algorithm}:{digest}").parse().ok(),
+                    false => None,
+                };
+            }
+            let (digest, name) = line.split_once(char::is_whitespace)?;
+            let name = name.trim_start().trim_start_matches('*');
+            // entries may contain a relative path such as `./dist/<name>`
+            match name == filename || name.rsplit('/').next() == Some(filename) {
+                true => digest.parse().ok(),
+                false => None,
+            }
+        })
+    }
+}
+
+/// # Checksum Source
+/// Where the expected checksum comes from, either given directly or looked up
+/// in a checksum file once the name of the downloaded file is known.
+#[derive(Debug, Clone)]
+pub enum ChecksumSource {
+    Digest(Checksum),
+    /// path or http(s) url of a checksum file such as `SHA256SUMS`
+    SumsFile(String),
+}
+
+impl ChecksumSource {
+    /// returns the expected checksum of the file with the given name, a
+    /// remote checksum file is fetched with the client of the download
+    pub(crate) async fn resolve(
+        &self,
+        filename: &str,
+        client: &reqwest::Client,
+    ) -> Result<Checksum, Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Digest(checksum) => Ok(checksum.clone()),
+            Self::SumsFile(path) => {
+                let content = match path.starts_with("http://") || path.starts_with("https://") {
+                    true => {
+                        client
+                            .get(path)
+                            .send()
+                            .await?
+                            .error_for_status()?
+                            .text()
+                            .await?
+                    }
+                    false => tokio::fs::read_to_string(path).await?,
+                };
+                Checksum::from_sums(&content, filename).ok_or_else(|| {
+                    VerificationError(format!("no checksum found for {filename:?} in {path}"))
+                        .into()
+                })
+            }
+        }
+    }
+}
+
+/// Hashes `len` bytes of the file starting at `start`, or the rest of the file
+/// when `len` is `None`. The digest is returned as a lowercase hex string.
+pub fn hash_file(
+    path: &str,
+    algorithm: Algorithm,
+    start: u64,
+    len: Option<u64>,
+) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut reader: Box<dyn Read> = match len {
+        Some(len) => Box::new(file.take(len)),
+        None => Box::new(file),
+    };
+
+    let mut hasher = algorithm.hasher();
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::TempDir;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn test_parse_checksum() {
+        let checksum: Checksum = format!("SHA256:{}", HELLO_SHA256.to_uppercase())
+            .parse()
+            .unwrap();
+        assert_eq!(checksum.algorithm, Algorithm::Sha256);
+        assert_eq!(checksum.digest, HELLO_SHA256);
+
+        let checksum: Checksum = "5d41402abc4b2a76b9719d911017c592".parse().unwrap();
+        assert_eq!(checksum.algorithm, Algorithm::Md5);
+
+        assert!("sha512:abcd".parse::<Checksum>().is_err());
+        assert!("sha1:not-hex".parse::<Checksum>().is_err());
+        assert!("sha1:abcd".parse::<Checksum>().is_err());
+        assert!(format!("md5:{HELLO_SHA256}").parse::<Checksum>().is_err());
+    }
+
+    #[test]
+    fn test_from_sums() {
+        let sums = format!(
+            "{}  other.iso\n{HELLO_SHA256} *./dist/hello.txt\n",
+            "0".repeat(64)
+        );
+        let checksum = Checksum::from_sums(&sums, "hello.txt").unwrap();
+        assert_eq!(checksum.digest, HELLO_SHA256);
+        assert!(Checksum::from_sums(&sums, "missing.txt").is_none());
+
+        let bsd = "SHA1 (hello.txt) = aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d";
+        let checksum = Checksum::from_sums(bsd, "hello.txt").unwrap();
+        assert_eq!(checksum.algorithm, Algorithm::Sha1);
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let dir = TempDir::new("checksum");
+        let path = dir.join("hello.txt");
+        std::fs::write(&path, "say hello").unwrap();
+        let full = hash_file(&path, Algorithm::Sha256, 0, None).unwrap();
+        let part = hash_file(&path, Algorithm::Sha256, 4, Some(5)).unwrap();
+        let md5 = hash_file(&path, Algorithm::Md5, 4, None).unwrap();
+
+        assert_ne!(full, HELLO_SHA256);
+        assert_eq!(part, HELLO_SHA256);
+        assert_eq!(md5, "5d41402abc4b2a76b9719d911017c592");
+    }
+}
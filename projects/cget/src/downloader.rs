@@ -1,38 +1,156 @@
-use std::{cmp::min, error::Error, sync::Arc, time::Duration};
+use std::{
+    error::Error,
+    io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use reqwest::{
-    self, Url,
-    header::{CONTENT_DISPOSITION, CONTENT_RANGE, HeaderMap, RANGE},
+    self, Client, Proxy, Url,
+    cookie::Jar,
+    header::{CONTENT_DISPOSITION, CONTENT_RANGE, CONTENT_TYPE, HeaderMap},
 };
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 // use tokio::stream;
 use futures_util::{StreamExt, future};
-use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
-use tokio::fs::File;
+use tokio::fs::{File, OpenOptions};
 use tokio::io::{AsyncSeekExt, AsyncWriteExt, SeekFrom};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore, mpsc::UnboundedSender};
+
+use crate::checksum::{Algorithm, Checksum, ChecksumSource, hash_file};
+use crate::error::{ErrorKind, VerificationError};
+use crate::events::DownloadEvent;
+use crate::filename::{
+    extension_from_content_type, parse_content_disposition, percent_decode, sanitize_filename,
+};
+use crate::mirror::Mirrors;
+use crate::retry::{ChunkError, RetryPolicy};
+use crate::source::{Source, source_for};
+use crate::state::{DownloadState, FileMeta};
+use crate::strategy::ChunkStrategy;
+use crate::throttle::RateLimiter;
+use crate::timestamp::{format_http_date, parse_http_date};
 
-#[derive(Debug)]
-struct Chunk {
-    start_byte: u64,
-    end_byte: u64,
-    downloaded: u64,
+/// default size of the in-memory buffer of every chunk task
+pub const DEFAULT_BUFFER_SIZE: usize = 1 << 20;
+/// minimum interval between two writes of the `<file>.cget` sidecar
+const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+/// interval between two measurements of the speed of a mirror
+const SPEED_INTERVAL: Duration = Duration::from_secs(1);
+/// a mirror is not compared with the others before a request ran this long
+const MIN_SPEED_SAMPLE: Duration = Duration::from_secs(3);
+/// default time to wait for a connection to the server
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// default time to wait for new bytes before the connection is considered
+/// stalled
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// State of a chunk during the current run, it is not persisted in the sidecar
+#[derive(Debug, Clone, Default)]
+pub(crate) enum ChunkStatus {
+    #[default]
+    Pending,
+    /// the chunk is being downloaded by a connection since the given instant,
+    /// `downloaded` is the number of bytes it already had at that point
+    Active {
+        since: Instant,
+        downloaded: u64,
+    },
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Chunk {
+    pub(crate) start_byte: u64,
+    pub(crate) end_byte: u64,
+    pub(crate) downloaded: u64,
+    /// SHA-256 of the bytes written for this chunk, it is only known when the
+    /// whole chunk was downloaded in a single run
+    #[serde(default)]
+    pub(crate) digest: Option<String>,
+    #[serde(skip)]
+    pub(crate) status: ChunkStatus,
+    #[serde(skip)]
+    hasher: Option<Sha256>,
 }
 impl Chunk {
-    fn new(start_byte: u64, end_byte: u64) -> Self {
+    pub(crate) fn new(start_byte: u64, end_byte: u64) -> Self {
         Self {
             start_byte,
             end_byte,
             downloaded: 0,
+            digest: None,
+            status: ChunkStatus::Pending,
+            hasher: None,
         }
     }
+
+    pub(crate) fn size(&self) -> u64 {
+        self.end_byte - self.start_byte + 1
+    }
+
+    pub(crate) fn remaining(&self) -> u64 {
+        self.size().saturating_sub(self.downloaded)
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        self.downloaded >= self.size()
+    }
+}
+
+/// # Existing File
+/// What happens when the file to download already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExistingFile {
+    /// the download fails and the file is kept as it is
+    #[default]
+    Refuse,
+    /// the file is downloaded again and replaced once the download is
+    /// complete
+    Overwrite,
+    /// the file is taken as the beginning of the download and only the rest
+    /// of it is downloaded
+    Continue,
 }
 
-#[derive(Debug)]
-pub(crate) struct Downloader {
+/// # Downloader
+/// Downloads a single file. It is configured with the `with_*` methods and
+/// reports its progress as [`DownloadEvent`]s.
+#[derive(Debug, Clone)]
+pub struct Downloader {
     url: String,
-    headers: HeaderMap,
+    /// headers sent with every request
+    request_headers: HeaderMap,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    proxy: Option<Proxy>,
+    cookies: Option<Arc<Jar>>,
+    /// skip the download when the existing file has not changed on the server
+    timestamping: bool,
+    existing_file: ExistingFile,
+    /// urls of other servers that serve the same file
+    mirror_urls: Vec<String>,
+    /// the url and the mirrors that passed the probe
+    mirrors: Arc<std::sync::Mutex<Mirrors>>,
+    /// the client shared by all connections, it is created by `download`
+    client: Option<Client>,
+    /// the client for the mirrors, which does not send the request headers
+    mirror_client: Option<Client>,
+    /// modification time of the file as an HTTP date
+    last_modified: Option<String>,
     file_size: Option<u64>,
+    ranges: bool,
     filename: Option<String>,
+    etag: Option<String>,
+    buffer_size: usize,
+    strategy: ChunkStrategy,
+    retry_policy: RetryPolicy,
+    checksum: Option<ChecksumSource>,
+    output_name: Option<String>,
+    connection_limit: Option<Arc<Semaphore>>,
+    rate_limit: Option<Arc<RateLimiter>>,
+    events: Option<UnboundedSender<DownloadEvent>>,
     chunks: Arc<Mutex<Vec<Chunk>>>, // this stores downloaded chunk size
 }
 
@@ -43,6 +161,11 @@ pub trait HeaderUtils {
     /// file name from the url and content-type too.
     fn extract_filename(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>>;
 
+    /// # Guess extension
+    /// When no file name is available at all, the extension of the file can
+    /// still be guessed from the content type.
+    fn guess_extension(&self) -> Option<&'static str>;
+
     /// # Extract file Size
     /// When response header provides content-range, it is easy to extract the
     /// actual file size in bytes.
@@ -56,13 +179,17 @@ pub trait HeaderUtils {
 impl HeaderUtils for HeaderMap {
     fn extract_filename(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         if let Some(disposition) = &self.get(CONTENT_DISPOSITION) {
-            let value = disposition.to_str()?;
-            if let Some(filename) = value.split("filename=").nth(1) {
-                return Ok(filename.trim_matches('"').to_string());
+            // the value may contain raw UTF-8 bytes that `to_str` rejects
+            let value = String::from_utf8_lossy(disposition.as_bytes());
+            if let Some(filename) = parse_content_disposition(&value) {
+                return Ok(filename);
             }
         }
-        return Err(Box::from("Unable to extract filename".to_owned()));
-        // TODO: guess filename from content type
+        Err(Box::from("Unable to extract filename".to_owned()))
+    }
+
+    fn guess_extension(&self) -> Option<&'static str> {
+        extension_from_content_type(self.get(CONTENT_TYPE)?.to_str().ok()?)
     }
 
     fn extract_file_size(&self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
@@ -70,7 +197,7 @@ impl HeaderUtils for HeaderMap {
             .get(CONTENT_RANGE)
             .ok_or_else(|| Box::<dyn Error + Send + Sync>::from("Content_range not found"))?;
         let content_range =
-            cr.to_str()?.split("/").into_iter().last().ok_or_else(|| {
+            cr.to_str()?.split('/').next_back().ok_or_else(|| {
                 Box::<dyn Error + Send + Sync>::from("Invalid Content_range_format")
             })?;
         Ok(content_range.parse()?)
@@ -82,219 +209,1330 @@ impl HeaderUtils for HeaderMap {
 /// For example: if content disposition is not provided, but there is a valid
 /// filename in the request url
 pub fn extract_filename_from_url(url: &str) -> Option<String> {
-    if let Ok(parsed_url) = Url::parse(&url) {
-        if let Some(segment) = parsed_url.path_segments().and_then(|s| s.last()) {
-            if !segment.is_empty() {
-                return Some(segment.to_string());
-            }
+    if let Ok(parsed_url) = Url::parse(url) {
+        if let Some(segment) = parsed_url.path_segments().and_then(|mut s| s.next_back()) {
+            let segment = String::from_utf8_lossy(&percent_decode(segment)).to_string();
+            return sanitize_filename(&segment);
         }
     }
-    return None;
+    None
 }
 
 impl Downloader {
     pub fn new(url: &str) -> Self {
         Self {
             url: url.to_owned(),
-            headers: HeaderMap::new(),
+            request_headers: HeaderMap::new(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            proxy: None,
+            cookies: None,
+            timestamping: false,
+            existing_file: ExistingFile::default(),
+            mirror_urls: Vec::new(),
+            mirrors: Arc::new(std::sync::Mutex::new(Mirrors::new(vec![url.to_owned()]))),
+            client: None,
+            mirror_client: None,
+            last_modified: None,
             file_size: None,
+            ranges: false,
             filename: None,
+            etag: None,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            strategy: ChunkStrategy::default(),
+            retry_policy: RetryPolicy::default(),
+            checksum: None,
+            output_name: None,
+            connection_limit: None,
+            rate_limit: None,
+            events: None,
             chunks: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
+    /// Sends the given headers with every request to the server of the url,
+    /// e.g. `Authorization`. They are not sent to the mirrors.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.request_headers = headers;
+        self
+    }
+
+    /// Sets how long to wait for a connection to the server and for new bytes
+    /// before a connection is considered stalled.
+    pub fn with_timeouts(mut self, connect_timeout: Duration, read_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    /// Sends all requests through the given proxy instead of the proxy of
+    /// the `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` environment variables.
+    pub fn with_proxy(mut self, proxy: Option<Proxy>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Keeps an existing file that has not changed on the server since it was
+    /// downloaded, and sets the modification time of downloaded files from
+    /// the `Last-Modified` header.
+    pub fn with_timestamping(mut self, timestamping: bool) -> Self {
+        self.timestamping = timestamping;
+        self
+    }
+
+    /// Downloads the chunks from the given mirrors as well. Mirrors that do
+    /// not serve the same file, fail or are much slower than the others are
+    /// dropped.
+    pub fn with_mirrors(mut self, mirrors: Vec<String>) -> Self {
+        self.mirror_urls = mirrors;
+        self
+    }
+
+    /// Sends the cookies of the given jar with every request.
+    pub fn with_cookies(mut self, cookies: Option<Arc<Jar>>) -> Self {
+        self.cookies = cookies;
+        self
+    }
+
+    /// returns the url of the file
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Sets the maximum number of bytes every chunk task keeps in memory
+    /// before writing them to the file.
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size.max(1);
+        self
+    }
+
+    /// Sets the number of connections and how the file is split into chunks.
+    pub fn with_strategy(mut self, strategy: ChunkStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the number of simultaneous connections.
+    pub fn with_connections(mut self, connections: usize) -> Self {
+        self.strategy.connections = connections.max(1);
+        self
+    }
+
+    /// Sets how often and how fast failed chunks are retried.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Verifies the downloaded file against the given checksum.
+    pub fn with_checksum(mut self, checksum: Option<ChecksumSource>) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Saves the file with the given name instead of the name suggested by
+    /// the server or the url.
+    pub fn with_output_name(mut self, output_name: Option<String>) -> Self {
+        self.output_name = output_name;
+        self
+    }
+
+    /// Shares a limit of simultaneous connections with other downloaders.
+    pub fn with_connection_limit(mut self, connection_limit: Arc<Semaphore>) -> Self {
+        self.connection_limit = Some(connection_limit);
+        self
+    }
+
+    /// Shares a limit of the total download speed with other downloaders.
+    pub fn with_rate_limit(mut self, rate_limit: Option<Arc<RateLimiter>>) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// Sets what happens when the file already exists. By default the
+    /// download fails instead of overwriting it.
+    pub fn with_existing_file(mut self, existing_file: ExistingFile) -> Self {
+        self.existing_file = existing_file;
+        self
+    }
+
+    /// Sends the events of this download to the given channel.
+    pub fn with_events(mut self, events: UnboundedSender<DownloadEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// sends an event to the listener, if there is any
+    fn emit(&self, event: DownloadEvent) {
+        if let Some(events) = &self.events {
+            // the listener may be gone, the download goes on anyway
+            let _ = events.send(event);
+        }
+    }
+
+    /// # Client
+    /// Returns the client of the downloader. It is created with the
+    /// configured timeouts, headers, proxy and cookies, so other requests to
+    /// the same server (e.g. crawling a directory listing) can use it as well.
+    pub fn client(&self) -> reqwest::Result<Client> {
+        if let Some(client) = &self.client {
+            return Ok(client.clone());
+        }
+        self.build_client(self.request_headers.clone())
+    }
+
+    /// Returns the client for the mirrors. The headers, e.g. the credentials
+    /// for the server of the url, are not sent to other servers, while the
+    /// cookies are only sent to the domains they belong to anyway.
+    fn mirror_client(&self) -> reqwest::Result<Client> {
+        if let Some(client) = &self.mirror_client {
+            return Ok(client.clone());
+        }
+        self.build_client(HeaderMap::new())
+    }
+
+    fn build_client(&self, headers: HeaderMap) -> reqwest::Result<Client> {
+        let mut builder = Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout)
+            .default_headers(headers);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        if let Some(cookies) = &self.cookies {
+            builder = builder.cookie_provider(Arc::clone(cookies));
+        }
+        builder.build()
+    }
+
+    /// returns the source that downloads the given url, picked by its scheme
+    fn source(&self, url: &str) -> Result<Arc<dyn Source>, ChunkError> {
+        let client = match self.is_origin(url) {
+            true => self.client()?,
+            false => self.mirror_client()?,
+        };
+        source_for(url, &client, self.connect_timeout, self.read_timeout)
+    }
+
+    /// returns whether the url is on the same server as the url of the download
+    fn is_origin(&self, url: &str) -> bool {
+        match (Url::parse(&self.url), Url::parse(url)) {
+            (Ok(origin), Ok(url)) => origin.origin() == url.origin(),
+            _ => false,
+        }
+    }
+
+    /// waits for a free connection when the number of connections is limited
+    async fn acquire_connection(&self) -> Option<OwnedSemaphorePermit> {
+        let limit = self.connection_limit.clone()?;
+        limit.acquire_owned().await.ok()
+    }
+
+    /// # Save State
+    /// Persists the progress of every chunk into the `<file>.cget` sidecar so
+    /// that the download can be resumed if the process gets killed.
+    async fn save_state(&self) -> std::io::Result<()> {
+        if !self.ranges {
+            return Ok(());
+        }
+        if let (Some(filename), Some(file_size)) = (&self.filename, self.file_size) {
+            // keep the chunks locked while saving so that concurrent tasks do
+            // not write the sidecar at the same time
+            let chunks = self.chunks.lock().await;
+            let state = DownloadState {
+                url: self.url.clone(),
+                file_size,
+                etag: self.etag.clone(),
+                chunks: chunks.clone(),
+            };
+            let path = DownloadState::sidecar_path(filename);
+            tokio::task::spawn_blocking(move || state.save(&path))
+                .await
+                .map_err(io::Error::other)??;
+        }
+        Ok(())
+    }
+
+    /// # Write Buffer
+    /// Writes the buffered bytes into the file at the given offset and moves
+    /// the progress of the chunk forward once the data is actually on disk.
+    async fn write_buffer(
+        &self,
+        file: &Arc<Mutex<File>>,
+        offset: u64,
+        buffer: &[u8],
+        chunk_index: Option<usize>,
+    ) -> std::io::Result<()> {
+        {
+            let mut f = file.lock().await;
+            f.seek(SeekFrom::Start(offset)).await?;
+            f.write_all(buffer).await?;
+            f.flush().await?;
+        }
+
+        if let Some(idx) = chunk_index {
+            let mut chunks = self.chunks.lock().await;
+            if idx < chunks.len() {
+                let chunk = &mut chunks[idx];
+                let written = (buffer.len() as u64).min(chunk.remaining());
+                if let Some(hasher) = &mut chunk.hasher {
+                    hasher.update(&buffer[..written as usize]);
+                }
+                chunk.downloaded += written;
+                if chunk.is_complete() {
+                    if let Some(hasher) = chunk.hasher.take() {
+                        chunk.digest = Some(hex::encode(hasher.finalize()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// writes the bytes that were received before a request was aborted, so
+    /// that a retry can continue from there
+    async fn save_partial(
+        &self,
+        file: &Option<Arc<Mutex<File>>>,
+        offset: u64,
+        buffer: &[u8],
+        chunk_index: Option<usize>,
+    ) -> Result<(), ChunkError> {
+        if let Some(file) = file {
+            self.write_buffer(file, offset, buffer, chunk_index).await?;
+        }
+        if chunk_index.is_some() {
+            self.save_state().await?;
+        }
+        Ok(())
+    }
+
     async fn get_chunk(
         &self,
         range: Option<(u64, u64)>,
-        progress_bar: Option<ProgressBar>,
+        mirror: usize,
+        connection: usize,
         file: Option<Arc<Mutex<File>>>,
         chunk_index: Option<usize>,
-    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
-        let client = reqwest::Client::new();
-        let mut builder = client.get(&self.url);
-        if let Some((start, end)) = range {
-            builder = builder.header(RANGE, &format!("bytes={start}-{end}"));
-        }
-        let response = builder.send().await?;
-        let mut stream = response.bytes_stream();
+    ) -> Result<u64, ChunkError> {
+        let url = self.mirrors.lock().unwrap().url(mirror).to_owned();
+        let mut stream = self.source(&url)?.read(&url, range).await?;
         let mut downloaded = 0u64;
-        let mut chunk_data = Vec::new();
 
-        // progress_bar
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            chunk_data.extend_from_slice(&chunk);
-            downloaded += chunk.len() as u64;
+        // bytes are collected only until the buffer is full, then written to
+        // the file at the current offset so memory stays bounded
+        let mut offset = range.map(|(start, _)| start).unwrap_or(0);
+        let mut buffer = Vec::with_capacity(self.buffer_size);
+        // position in the file up to which the bytes were received
+        let mut received = offset;
+        let mut last_saved = Instant::now();
+        let started = Instant::now();
+        let mut last_measured = started;
+
+        while let Some(bytes) = stream.next().await {
+            let bytes = match bytes {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    self.save_partial(&file, offset, &buffer, chunk_index)
+                        .await?;
+                    return Err(e);
+                }
+            };
+            // waiting here keeps the bytes in the socket, so the server has to
+            // slow down as well
+            if let Some(rate_limit) = &self.rate_limit {
+                rate_limit.acquire(bytes.len()).await;
+            }
+            let mut bytes = &bytes[..];
+            let mut finished = false;
+            let (mut start, mut size) = (0, self.file_size);
 
-            if let Some(bar) = &progress_bar {
-                bar.inc(chunk.len() as u64);
+            // another connection may have taken over the end of this chunk, so
+            // only the bytes up to the current end of the chunk are kept
+            if let Some((chunk_start, end)) = self.chunk_range(chunk_index).await {
+                (start, size) = (chunk_start, Some(end - chunk_start + 1));
+                let limit = (end + 1).saturating_sub(offset) as usize;
+                buffer.truncate(limit);
+                let allowed = limit - buffer.len();
+                if bytes.len() >= allowed {
+                    bytes = &bytes[..allowed];
+                    finished = true;
+                }
             }
+            buffer.extend_from_slice(bytes);
+            received += bytes.len() as u64;
+            self.emit(DownloadEvent::ChunkProgress {
+                connection,
+                chunk: chunk_index.unwrap_or(0),
+                downloaded: received - start,
+                size,
+            });
 
-            // Update chunk progress in shared state
-            if let Some(idx) = chunk_index {
-                let mut chunks = self.chunks.lock().await;
-                if idx < chunks.len() {
-                    chunks[idx].downloaded = downloaded;
+            // compare the speed of the mirror with the other mirrors from time
+            // to time, a mirror that is much slower gives its chunk away
+            if chunk_index.is_some() && !finished && last_measured.elapsed() >= SPEED_INTERVAL {
+                last_measured = Instant::now();
+                let elapsed = started.elapsed();
+                let speed =
+                    (received - range.map_or(0, |(start, _)| start)) as f64 / elapsed.as_secs_f64();
+                let too_slow = {
+                    let mut mirrors = self.mirrors.lock().unwrap();
+                    mirrors.record_speed(mirror, speed);
+                    // another connection may have dropped the mirror already
+                    mirrors.is_dropped(mirror)
+                        || elapsed >= MIN_SPEED_SAMPLE && mirrors.is_too_slow(mirror)
+                };
+                if too_slow {
+                    self.save_partial(&file, offset, &buffer, chunk_index)
+                        .await?;
+                    return Err(ChunkError::transient(
+                        "the mirror is much slower than the others",
+                    ));
                 }
             }
-        }
 
-        // Write to file at correct position
-        if let (Some(file), Some((start, _))) = (file, range) {
-            let mut f = file.lock().await;
-            f.seek(SeekFrom::Start(start)).await?;
-            f.write_all(&chunk_data).await?;
+            if buffer.len() >= self.buffer_size || finished {
+                if let Some(file) = &file {
+                    self.write_buffer(file, offset, &buffer, chunk_index)
+                        .await?;
+                }
+                offset += buffer.len() as u64;
+                downloaded += buffer.len() as u64;
+                buffer.clear();
+
+                // persist the progress from time to time instead of on every write
+                if chunk_index.is_some() && last_saved.elapsed() >= STATE_SAVE_INTERVAL {
+                    self.save_state().await?;
+                    last_saved = Instant::now();
+                }
+            }
+            if finished {
+                break;
+            }
         }
 
-        if let Some(bar) = progress_bar {
-            bar.finish();
+        if !buffer.is_empty() {
+            if let Some(file) = &file {
+                self.write_buffer(file, offset, &buffer, chunk_index)
+                    .await?;
+            }
+            downloaded += buffer.len() as u64;
+        }
+        if chunk_index.is_some() {
+            self.save_state().await?;
+            // requests that are shorter than the interval are measured here
+            let speed = downloaded as f64 / started.elapsed().as_secs_f64();
+            if speed.is_finite() {
+                self.mirrors.lock().unwrap().record_speed(mirror, speed);
+            }
         }
 
         Ok(downloaded)
     }
 
-    pub async fn download(
-        &mut self,
-        path: &str,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let client = reqwest::Client::new();
-        // get response headers to get file name, length, etc.
-        let response = client
-            .get(&self.url)
-            .header(RANGE, "bytes=0-0")
-            .send()
+    /// returns the number of bytes of all chunks that are already on disk
+    async fn downloaded_bytes(&self) -> u64 {
+        let chunks = self.chunks.lock().await;
+        chunks.iter().map(|chunk| chunk.downloaded).sum()
+    }
+
+    /// returns the current byte range of the chunk with the given index
+    async fn chunk_range(&self, chunk_index: Option<usize>) -> Option<(u64, u64)> {
+        let idx = chunk_index?;
+        let chunks = self.chunks.lock().await;
+        chunks
+            .get(idx)
+            .map(|chunk| (chunk.start_byte, chunk.end_byte))
+    }
+
+    /// # Connection
+    /// A connection keeps downloading the chunks picked by the chunk strategy
+    /// until there is nothing left to download.
+    async fn connection(
+        &self,
+        file: Arc<Mutex<File>>,
+        connection: usize,
+    ) -> Result<(), ChunkError> {
+        loop {
+            let idx = {
+                let mut chunks = self.chunks.lock().await;
+                match self.strategy.next_chunk(&mut chunks) {
+                    Some(idx) => idx,
+                    None => break,
+                }
+            };
+
+            let (start, end) = self.chunk_range(Some(idx)).await.unwrap_or_default();
+            self.emit(DownloadEvent::ChunkStarted {
+                connection,
+                chunk: idx,
+                start,
+                end,
+            });
+            let result = self.download_chunk(idx, &file, connection).await;
+            let mut chunks = self.chunks.lock().await;
+            match result {
+                Ok(()) => {
+                    chunks[idx].status = ChunkStatus::Pending;
+                    self.emit(DownloadEvent::ChunkCompleted {
+                        connection,
+                        chunk: idx,
+                    });
+                }
+                Err(e) => {
+                    chunks[idx].status = ChunkStatus::Failed;
+                    self.emit(DownloadEvent::ChunkFailed {
+                        connection,
+                        chunk: idx,
+                        error: e.to_string(),
+                    });
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// # Download Chunk
+    /// Downloads the chunk with the given index, retrying transient errors
+    /// with an exponential backoff. Every retry continues from the bytes of
+    /// the chunk that are already on disk.
+    ///
+    /// When a mirror fails while other mirrors are left, the mirror is dropped
+    /// and the chunk continues right away from another mirror.
+    async fn download_chunk(
+        &self,
+        idx: usize,
+        file: &Arc<Mutex<File>>,
+        connection: usize,
+    ) -> Result<(), ChunkError> {
+        let mut retry = 0;
+        loop {
+            let chunk = {
+                let mut chunks = self.chunks.lock().await;
+                let chunk = &mut chunks[idx];
+                // the chunk can only be hashed while it is downloaded from the start
+                if self.checksum.is_some() && chunk.downloaded == 0 {
+                    chunk.hasher = Some(Sha256::new());
+                }
+                chunk.clone()
+            };
+            self.emit(DownloadEvent::ChunkProgress {
+                connection,
+                chunk: idx,
+                downloaded: chunk.downloaded,
+                size: Some(chunk.size()),
+            });
+
+            // Download only the missing part of the chunk
+            let permit = self.acquire_connection().await;
+            let mirror = self.mirrors.lock().unwrap().pick();
+            let mut result = self
+                .get_chunk(
+                    Some((chunk.start_byte + chunk.downloaded, chunk.end_byte)),
+                    mirror,
+                    connection,
+                    Some(Arc::clone(file)),
+                    Some(idx),
+                )
+                .await
+                .map(|_| ());
+            self.mirrors.lock().unwrap().release(mirror);
+            drop(permit);
+            if result.is_ok() && !self.chunks.lock().await[idx].is_complete() {
+                result = Err(ChunkError::transient(
+                    "the connection was closed before the chunk was complete",
+                ));
+            }
+
+            if let Err(e) = &result {
+                let mut mirrors = self.mirrors.lock().unwrap();
+                if mirrors.drop_mirror(mirror) {
+                    self.emit(DownloadEvent::MirrorDropped {
+                        url: mirrors.url(mirror).to_owned(),
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+                // another connection dropped the mirror in the meantime
+                if mirrors.is_dropped(mirror) {
+                    continue;
+                }
+            }
+
+            match result {
+                Err(e) if e.is_transient() && retry < self.retry_policy.max_retries => {
+                    retry += 1;
+                    let delay = self.retry_policy.backoff(retry);
+                    self.emit(DownloadEvent::ChunkRetried {
+                        connection,
+                        chunk: idx,
+                        retry,
+                        max_retries: self.retry_policy.max_retries,
+                        delay,
+                        error: e.to_string(),
+                    });
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// # Download Stream
+    /// Downloads the whole file in a single request when the server does not
+    /// support ranges. Transient errors are retried with the same backoff as
+    /// the chunks, but every retry has to start from the beginning again.
+    async fn download_stream(&self, file: &Arc<Mutex<File>>) -> Result<u64, ChunkError> {
+        let mut retry = 0;
+        loop {
+            let permit = self.acquire_connection().await;
+            let mut result = self
+                .get_chunk(None, 0, 0, Some(Arc::clone(file)), None)
+                .await;
+            drop(permit);
+            if let (Ok(downloaded), Some(size)) = (&result, self.file_size) {
+                if *downloaded < size {
+                    result = Err(ChunkError::transient(
+                        "the connection was closed before the file was complete",
+                    ));
+                }
+            }
+
+            match result {
+                Err(e) if e.is_transient() && retry < self.retry_policy.max_retries => {
+                    retry += 1;
+                    let delay = self.retry_policy.backoff(retry);
+                    self.emit(DownloadEvent::ChunkRetried {
+                        connection: 0,
+                        chunk: 0,
+                        retry,
+                        max_retries: self.retry_policy.max_retries,
+                        delay,
+                        error: e.to_string(),
+                    });
+                    tokio::time::sleep(delay).await;
+                    // the bytes of the failed attempt can not be continued
+                    file.lock().await.set_len(0).await?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// # Verify
+    /// Hashes the downloaded file and compares it with the expected checksum.
+    ///
+    /// On a mismatch, every chunk is hashed again and compared with the digest
+    /// of the bytes that were received for it. Chunks that differ (or whose
+    /// digest is unknown) are reset in the sidecar so that the next run only
+    /// downloads them again. If all chunks still match, the server sent wrong
+    /// data and every chunk is reset.
+    async fn verify(&self, checksum: &Checksum) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let path = self
+            .filename
+            .as_deref()
+            .map(DownloadState::part_path)
+            .ok_or("the file name is not known yet")?;
+        let algorithm = checksum.algorithm;
+        let file_path = path.clone();
+        let actual = tokio::task::spawn_blocking(move || hash_file(&file_path, algorithm, 0, None))
+            .await??;
+        if actual == checksum.digest {
+            self.emit(DownloadEvent::Verified {
+                checksum: checksum.to_string(),
+            });
+            return Ok(());
+        }
+
+        let mut reset = 0;
+        if self.ranges {
+            let mut chunks = self.chunks.lock().await.clone();
+            let mut chunks = tokio::task::spawn_blocking(move || {
+                for chunk in chunks.iter_mut() {
+                    let digest = hash_file(
+                        &path,
+                        Algorithm::Sha256,
+                        chunk.start_byte,
+                        Some(chunk.size()),
+                    )
+                    .ok();
+                    if digest.is_none() || digest != chunk.digest {
+                        *chunk = Chunk::new(chunk.start_byte, chunk.end_byte);
+                    }
+                }
+                chunks
+            })
             .await?;
-        self.headers = response.headers().clone().to_owned();
+            reset = chunks.iter().filter(|chunk| chunk.downloaded == 0).count();
+            if reset == 0 {
+                chunks = chunks
+                    .iter()
+                    .map(|chunk| Chunk::new(chunk.start_byte, chunk.end_byte))
+                    .collect();
+                reset = chunks.len();
+            }
+            *self.chunks.lock().await = chunks;
+            self.save_state().await?;
+        }
+
+        Err(VerificationError(format!(
+            "checksum mismatch: expected {checksum}, got {algorithm}:{actual} ({reset} chunks will be downloaded again)"
+        ))
+        .into())
+    }
+
+    /// # Is Up To Date
+    /// With timestamping, asks the source whether the existing file has
+    /// changed since the stored `ETag` and `Last-Modified` (or the
+    /// modification time of the file). Over HTTP, they are sent in
+    /// `If-None-Match` and `If-Modified-Since`, and `304 Not Modified` means
+    /// that the file is kept as it is.
+    ///
+    /// Returns `None` when there is no file or it can not be told whether it
+    /// has changed.
+    async fn is_up_to_date(&self, source: &dyn Source, filename: &str) -> Option<bool> {
+        let metadata = tokio::fs::metadata(filename).await.ok()?;
+        // an interrupted download of the newer version is resumed
+        let state = DownloadState::load(&DownloadState::sidecar_path(filename));
+        if let (Some(state), Some(size)) = (state, self.file_size) {
+            if state.matches(&self.url, size, self.etag.as_deref()) {
+                return Some(false);
+            }
+        }
+        if self.file_size.is_some_and(|size| size != metadata.len()) {
+            return Some(false);
+        }
+        let meta =
+            FileMeta::load(&FileMeta::meta_path(filename)).filter(|meta| meta.url == self.url);
+        let etag = meta.as_ref().and_then(|meta| meta.etag.clone());
+        let modified_since = meta
+            .and_then(|meta| meta.last_modified)
+            .or_else(|| metadata.modified().ok().map(format_http_date));
+
+        let _permit = self.acquire_connection().await;
+        source
+            .not_modified(&self.url, etag.as_deref(), modified_since.as_deref())
+            .await
+    }
+
+    /// # Save Timestamps
+    /// Sets the modification time of the downloaded file from the
+    /// `Last-Modified` header and stores the validators of the file for the
+    /// next run.
+    fn save_timestamps(&self, filename: &str) -> std::io::Result<()> {
+        let last_modified = self.last_modified.clone();
+        if let Some(time) = last_modified.as_deref().and_then(parse_http_date) {
+            std::fs::File::options()
+                .write(true)
+                .open(filename)?
+                .set_modified(time)?;
+        }
+        FileMeta {
+            url: self.url.clone(),
+            etag: self.etag.clone(),
+            last_modified,
+        }
+        .save(&FileMeta::meta_path(filename))
+    }
+
+    /// # Probe Mirrors
+    /// Keeps the mirrors that serve the same file as the url: they must
+    /// support ranges and report the same size and, if both servers send one,
+    /// the same `ETag`.
+    async fn probe_mirrors(&self) -> Mirrors {
+        let probes = self.mirror_urls.iter().map(|url| async move {
+            let metadata = match self.source(url) {
+                Ok(source) => source.metadata(url).await,
+                Err(e) => Err(e),
+            };
+            (url, metadata)
+        });
+        let mut urls = vec![self.url.clone()];
+        for (url, result) in future::join_all(probes).await {
+            let reason = match result {
+                Err(e) => Some(e.to_string()),
+                Ok(info) if !info.ranges => Some("the mirror does not support ranges".to_owned()),
+                Ok(info) if info.file_size != self.file_size => Some(format!(
+                    "the mirror reports a different size ({:?} instead of {:?})",
+                    info.file_size, self.file_size
+                )),
+                Ok(info) => match (&info.etag, &self.etag) {
+                    (Some(mirror), Some(etag)) if mirror != etag => Some(format!(
+                        "the mirror reports a different ETag ({mirror} instead of {etag})"
+                    )),
+                    _ => None,
+                },
+            };
+            match reason {
+                Some(reason) => self.emit(DownloadEvent::MirrorDropped {
+                    url: url.clone(),
+                    reason,
+                }),
+                None => urls.push(url.clone()),
+            }
+        }
+        Mirrors::new(urls)
+    }
+
+    /// # Download
+    /// Downloads the file into the given directory and returns the number of
+    /// bytes that were downloaded in this run. Ends with a `Completed` or a
+    /// `Failed` event.
+    pub async fn download(&mut self, path: &str) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let result = self.run(path).await;
+        match &result {
+            Ok(bytes) => self.emit(DownloadEvent::Completed {
+                path: self.filename.clone().unwrap_or_default(),
+                bytes: *bytes,
+            }),
+            Err(e) => self.emit(DownloadEvent::Failed {
+                error: e.to_string(),
+                kind: ErrorKind::of(e.as_ref()),
+            }),
+        }
+        result
+    }
+
+    async fn run(&mut self, path: &str) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        // the client is created once and shared by all connections so that
+        // they reuse its connection pool
+        let client = self.client()?;
+        self.client = Some(client.clone());
+        if !self.mirror_urls.is_empty() {
+            self.mirror_client = Some(self.mirror_client()?);
+        }
+        let source = self.source(&self.url)?;
+        // get the file name, length, etc.
+        let permit = self.acquire_connection().await;
+        let info = source.metadata(&self.url).await?;
+        drop(permit);
+        self.file_size = info.file_size;
+        self.ranges = info.ranges;
+        self.etag = info.etag;
+        self.last_modified = info.last_modified;
 
-        let filename = match &self.headers.extract_filename() {
-            Ok(filename) => filename.to_owned(),
-            Err(_) => match extract_filename_from_url(&self.url) {
+        let name = match (&self.output_name, info.filename) {
+            (Some(name), _) => name.to_owned(),
+            (None, Some(filename)) => filename,
+            (None, None) => match extract_filename_from_url(&self.url) {
                 Some(filename) => filename,
-                None => "download.bin".to_owned(),
+                None => format!("download.{}", info.extension.unwrap_or("bin")),
             },
         };
-        println!("⛔filename: {filename}");
         // trim trailing / from original path
-        self.filename = Some(format!("{path}/{filename}").replace("//", "/"));
+        let filename = format!("{path}/{name}").replace("//", "/");
+        self.filename = Some(filename.clone());
 
-        if let Ok(file_size) = self.headers.extract_file_size() {
-            self.file_size = Some(file_size);
-            println!("⛔file size: {}", HumanBytes(file_size));
-        } else {
-            println!("⛔ Unable to determine the file size. skipping threads")
+        let up_to_date = match self.timestamping {
+            true => self.is_up_to_date(source.as_ref(), &filename).await,
+            false => None,
+        };
+        if up_to_date == Some(true) {
+            self.emit(DownloadEvent::NotModified { path: filename });
+            return Ok(0);
         }
 
-        let file = Arc::new(Mutex::new(
-            File::create(self.filename.as_ref().unwrap()).await?,
-        ));
+        // the file is downloaded into `<file>.part`, so that a file with the
+        // final name is always complete
+        let part = DownloadState::part_path(&filename);
+        if tokio::fs::try_exists(&filename).await? {
+            match self.existing_file {
+                // with timestamping, a file that changed on the server is replaced
+                _ if up_to_date == Some(false) => {}
+                ExistingFile::Refuse => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{filename} already exists"),
+                    )
+                    .into());
+                }
+                ExistingFile::Overwrite => {}
+                ExistingFile::Continue => {
+                    let len = tokio::fs::metadata(&filename).await?.len();
+                    if self.file_size.is_some_and(|size| len > size) {
+                        return Err(format!("{filename} is larger than the remote file").into());
+                    }
+                    // the partial file of an earlier run has precedence, and
+                    // without ranges the download starts over in the partial
+                    // file while the existing file is kept until it is done
+                    if self.ranges
+                        && self.file_size.is_some()
+                        && !tokio::fs::try_exists(&part).await?
+                    {
+                        tokio::fs::rename(&filename, &part).await?;
+                    }
+                }
+            }
+        }
 
         // handle chunks with threads
-        if let Some(file_size) = self.file_size {
-            // allocate file's size if size is known
-            // this helps seeking to the position and writing the chunk at that position
-            file.lock().await.set_len(file_size).await?;
-
-            let mut start = 0;
-            let byte_size = file_size / 8;
-
-            // split chunks to download
-            while start < file_size {
-                let end = min(start + byte_size, file_size);
-                self.chunks.lock().await.push(Chunk::new(start, end));
-                start = end + 1;
+        let total_downloaded = if let (Some(file_size), true) = (self.file_size, self.ranges) {
+            if !self.mirror_urls.is_empty() {
+                let mirrors = self.probe_mirrors().await;
+                *self.mirrors.lock().unwrap() = mirrors;
             }
+            let state_path = DownloadState::sidecar_path(&filename);
 
-            let num_chunks = self.chunks.lock().await.len();
-            println!("Created {} chunks for download", num_chunks);
+            // resume from the sidecar only if it still describes the same
+            // remote file and the partially downloaded file is still there
+            let resumed = match DownloadState::load(&state_path) {
+                Some(state) if state.matches(&self.url, file_size, self.etag.as_deref()) => {
+                    match OpenOptions::new().write(true).open(&part).await {
+                        Ok(file) => Some((state.chunks, file)),
+                        Err(_) => None,
+                    }
+                }
+                // without a sidecar, the existing bytes of the file are the
+                // beginning of the download
+                _ if self.existing_file == ExistingFile::Continue => {
+                    match OpenOptions::new().write(true).open(&part).await {
+                        Ok(file) => {
+                            let len = file.metadata().await?.len().min(file_size);
+                            let mut chunks = self.strategy.split(file_size);
+                            for chunk in &mut chunks {
+                                chunk.downloaded =
+                                    len.saturating_sub(chunk.start_byte).min(chunk.size());
+                            }
+                            file.set_len(file_size).await?;
+                            Some((chunks, file))
+                        }
+                        Err(_) => None,
+                    }
+                }
+                _ => None,
+            };
+
+            let file = match resumed {
+                Some((chunks, file)) => {
+                    *self.chunks.lock().await = chunks;
+                    file
+                }
+                None => {
+                    let file = File::create(&part).await?;
+                    // allocate file's size if size is known
+                    // this helps seeking to the position and writing the chunk at that position
+                    // (the file is sparse until then, which is fine for a `.part` file)
+                    file.set_len(file_size).await?;
+
+                    // split chunks to download
+                    *self.chunks.lock().await = self.strategy.split(file_size);
+                    file
+                }
+            };
+            let file = Arc::new(Mutex::new(file));
+            self.save_state().await?;
+            let resumed_bytes = self.downloaded_bytes().await;
 
-            let multi_progress = Arc::new(MultiProgress::new());
+            let num_chunks = self.chunks.lock().await.len();
+            let connections = self.strategy.connections.clamp(1, num_chunks.max(1));
+            self.emit(DownloadEvent::Started {
+                url: self.url.clone(),
+                path: filename.clone(),
+                file_size: Some(file_size),
+                ranges: true,
+                chunks: num_chunks,
+                connections,
+                mirrors: self.mirrors.lock().unwrap().active(),
+                resumed: resumed_bytes,
+            });
 
             // Create tasks for concurrent downloading
             let mut tasks = Vec::new();
-            let chunks_clone = Arc::clone(&self.chunks);
-
-            for i in 0..num_chunks {
-                let chunks = Arc::clone(&chunks_clone);
+            for connection in 0..connections {
+                // chunks are shared between the clones of the downloader
+                let downloader = self.clone();
                 let file_clone = Arc::clone(&file);
-                let url = self.url.clone();
-                let multi_progress_clone = Arc::clone(&multi_progress);
-
-                let task = tokio::spawn(async move {
-                    // Get chunk info
-                    let (start, end) = {
-                        let chunks_guard = chunks.lock().await;
-                        if i >= chunks_guard.len() {
-                            return Err("Chunk index out of bounds".into());
-                        }
-                        (chunks_guard[i].start_byte, chunks_guard[i].end_byte)
-                    };
-
-                    // Create progress bar for this chunk
-                    let chunk_size = end - start + 1;
-                    let progress_bar = multi_progress_clone.add(ProgressBar::new(chunk_size));
-                    progress_bar.set_style(ProgressStyle::with_template(
-                        &format!("[Chunk {}] {{wide_bar:40.cyan/blue}} {{binary_bytes}}/{{binary_total_bytes}} ({{percent}}%)", i)
-                    ).unwrap());
-
-                    // Create a downloader instance for this chunk
-                    let downloader = Downloader {
-                        url,
-                        headers: HeaderMap::new(),
-                        file_size: None,
-                        filename: None,
-                        chunks: chunks,
-                    };
-
-                    // Download the chunk
-                    downloader
-                        .get_chunk(
-                            Some((start, end)),
-                            Some(progress_bar),
-                            Some(file_clone),
-                            Some(i),
-                        )
-                        .await
-                });
-
-                tasks.push(task);
+                tasks.push(tokio::spawn(async move {
+                    downloader.connection(file_clone, connection).await
+                }));
             }
 
             // Wait for all downloads to complete
-            println!("Starting concurrent downloads...");
             let results = future::try_join_all(tasks)
                 .await
-                .map_err(|e| format!("Task join error: {}", e))?;
-
-            let total_downloaded: u64 = results
-                .into_iter()
-                .collect::<Result<Vec<_>, _>>()?
-                .into_iter()
-                .sum();
-
-            println!(
-                "Download completed! Total bytes: {}",
-                HumanBytes(total_downloaded)
-            );
+                .map_err(|e| format!("Task join error: {e}"))?;
+
+            let total_downloaded = self.downloaded_bytes().await - resumed_bytes;
+
+            // the sidecar is kept when a chunk failed so that the next run
+            // can resume from there
+            results.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+            // the sidecar is kept until the checksum is verified so that the
+            // corrupt chunks can be downloaded again
+            file.lock().await.sync_all().await?;
+            if let Some(source) = &self.checksum {
+                self.verify(&source.resolve(&name, &client).await?).await?;
+            }
+            tokio::fs::rename(&part, &filename).await?;
+
+            // every chunk is on disk, so the sidecar is no longer needed
+            DownloadState::remove(&state_path);
+            total_downloaded
         } else {
-            let file_clone = Arc::clone(&file);
-            let bar = ProgressBar::new_spinner();
-            bar.enable_steady_tick(Duration::from_millis(100));
-            println!("");
-            bar.set_style(
-                ProgressStyle::with_template(&format!(
-                    "{{spinner:.cyan}} {:?} ({{binary_bytes}} downloaded)",
-                    self.filename.as_ref().unwrap()
-                ))
-                .unwrap()
-                // .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏"),
-                // set tick character as a moon's phase as progress indicator
-                .tick_chars("🌑🌒🌓🌔🌕🌖🌗🌘"),
-            );
-            let _ = self
-                .get_chunk(None, Some(bar), Some(file_clone), None)
-                .await;
+            for url in &self.mirror_urls {
+                self.emit(DownloadEvent::MirrorDropped {
+                    url: url.clone(),
+                    reason: "the file is downloaded in a single stream from the url".to_owned(),
+                });
+            }
+            let file = Arc::new(Mutex::new(File::create(&part).await?));
+            self.emit(DownloadEvent::Started {
+                url: self.url.clone(),
+                path: filename.clone(),
+                file_size: self.file_size,
+                ranges: false,
+                chunks: 1,
+                connections: 1,
+                mirrors: 1,
+                resumed: 0,
+            });
+            let total_downloaded = self.download_stream(&file).await?;
+
+            file.lock().await.sync_all().await?;
+            if let Some(source) = &self.checksum {
+                self.verify(&source.resolve(&name, &client).await?).await?;
+            }
+            tokio::fs::rename(&part, &filename).await?;
+            total_downloaded
+        };
+
+        if self.timestamping {
+            self.save_timestamps(&filename)?;
         }
+        Ok(total_downloaded)
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Behaviour, TempDir, serve, serve_ftp, test_body};
+    use std::sync::atomic::Ordering;
+
+    /// downloads `file.bin` from a stand-in server into a new temp directory
+    /// and returns the directory and the number of `GET` requests
+    async fn download(behaviour: Behaviour, body: &[u8], name: &str) -> (TempDir, usize) {
+        let (url, server) = serve(behaviour, body.to_vec()).await;
+        let dir = TempDir::new(name);
+        let strategy = ChunkStrategy {
+            connections: 4,
+            min_chunk_size: 16 << 10,
+            max_chunk_size: Some(64 << 10),
+            adaptive: false,
+        };
+        Downloader::new(&format!("{url}/file.bin"))
+            .with_strategy(strategy)
+            .download(dir.path())
+            .await
+            .unwrap();
+        (dir, server.requests.load(Ordering::SeqCst))
+    }
+
+    fn assert_downloaded(dir: &str, body: &[u8]) {
+        let path = format!("{dir}/file.bin");
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+        assert!(!std::path::Path::new(&DownloadState::sidecar_path(&path)).exists());
+        assert!(!std::path::Path::new(&DownloadState::part_path(&path)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_in_chunks() {
+        let body = test_body(300_000);
+        let (dir, requests) = download(Behaviour::Ranges, &body, "ranges").await;
+        // the probe and at least one request per chunk
+        assert!(requests > 5);
+        assert_downloaded(dir.path(), &body);
+
+        let (dir, requests) = download(Behaviour::NoHead, &body, "no-head").await;
+        assert!(requests > 5);
+        assert_downloaded(dir.path(), &body);
+    }
+
+    #[tokio::test]
+    async fn test_download_in_single_stream() {
+        let body = test_body(300_000);
+        let (dir, requests) = download(Behaviour::IgnoresRanges, &body, "ignores").await;
+        assert_eq!(requests, 2);
+        assert_downloaded(dir.path(), &body);
+
+        // the range probe is skipped
+        let (dir, requests) = download(Behaviour::AcceptRangesNone, &body, "none").await;
+        assert_eq!(requests, 1);
+        assert_downloaded(dir.path(), &body);
+
+        let (dir, requests) = download(Behaviour::NoContentLength, &body, "chunked").await;
+        assert_eq!(requests, 2);
+        assert_downloaded(dir.path(), &body);
+    }
+
+    #[tokio::test]
+    async fn test_retry_single_stream() {
+        let body = test_body(300_000);
+        let (url, server) = serve(Behaviour::CutOffOnce, body.clone()).await;
+        let dir = TempDir::new("stream-retry");
+        Downloader::new(&format!("{url}/file.bin"))
+            .with_retry_policy(RetryPolicy {
+                max_retries: 1,
+                delay: Duration::ZERO,
+            })
+            .download(dir.path())
+            .await
+            .unwrap();
+        // the whole file is requested again
+        assert_eq!(server.requests.load(Ordering::SeqCst), 2);
+        assert_downloaded(dir.path(), &body);
+    }
+
+    #[tokio::test]
+    async fn test_download_empty_file() {
+        let (dir, _) = download(Behaviour::Ranges, &[], "empty").await;
+        assert_downloaded(dir.path(), &[]);
+    }
+
+    #[tokio::test]
+    async fn test_download_events() {
+        let (url, _) = serve(Behaviour::Ranges, test_body(100_000)).await;
+        let dir = TempDir::new("events");
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut downloader = Downloader::new(&format!("{url}/file.bin"))
+            .with_connections(2)
+            .with_events(sender);
+        downloader.download(dir.path()).await.unwrap();
+        drop(downloader);
+
+        let mut events = Vec::new();
+        while let Some(event) = receiver.recv().await {
+            events.push(event);
+        }
+        let path = dir.join("file.bin");
+        assert!(matches!(
+            &events[0],
+            DownloadEvent::Started { path: p, file_size: Some(100_000), ranges: true, .. } if *p == path
+        ));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            DownloadEvent::ChunkProgress { downloaded, size: Some(size), .. } if downloaded == size
+        )));
+        assert_eq!(
+            events.last(),
+            Some(&DownloadEvent::Completed {
+                path,
+                bytes: 100_000
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_from_mirrors() {
+        let body = test_body(300_000);
+        let (url, primary) = serve(Behaviour::Ranges, body.clone()).await;
+        let (mirror, server) = serve(Behaviour::NoHead, body.clone()).await;
+        let (failing, _) = serve(Behaviour::FailsAfterProbe, body.clone()).await;
+        let (missing, _) = serve(Behaviour::NotFound, body.clone()).await;
+        let (other, _) = serve(Behaviour::Ranges, test_body(1000)).await;
+        let dir = TempDir::new("mirrors");
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let strategy = ChunkStrategy {
+            connections: 4,
+            min_chunk_size: 16 << 10,
+            max_chunk_size: Some(32 << 10),
+            adaptive: false,
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            "Bearer secret".parse().unwrap(),
+        );
+        let mut downloader = Downloader::new(&format!("{url}/file.bin"))
+            .with_strategy(strategy)
+            .with_headers(headers)
+            .with_retry_policy(RetryPolicy {
+                max_retries: 0,
+                delay: Duration::ZERO,
+            })
+            .with_mirrors(
+                [&mirror, &failing, &missing, &other]
+                    .map(|url| format!("{url}/file.bin"))
+                    .to_vec(),
+            )
+            .with_events(sender);
+        downloader.download(dir.path()).await.unwrap();
+        drop(downloader);
+
+        let mut dropped = Vec::new();
+        while let Some(event) = receiver.recv().await {
+            if let DownloadEvent::MirrorDropped { url, .. } = event {
+                dropped.push(url);
+            }
+        }
+        dropped.sort();
+        let mut expected: Vec<String> = [&failing, &missing, &other]
+            .map(|url| format!("{url}/file.bin"))
+            .to_vec();
+        expected.sort();
+        assert_eq!(dropped, expected);
+        // the chunks were spread across the url and the working mirror
+        assert!(primary.requests.load(Ordering::SeqCst) > 2);
+        assert!(server.requests.load(Ordering::SeqCst) > 2);
+        // the credentials are only sent to the server of the url
+        assert!(primary.authorized.load(Ordering::SeqCst) > 2);
+        assert_eq!(server.authorized.load(Ordering::SeqCst), 0);
+        assert_downloaded(dir.path(), &body);
+    }
+
+    #[tokio::test]
+    async fn test_download_timestamping() {
+        let body = test_body(100_000);
+        let (url, server) = serve(Behaviour::Ranges, body.clone()).await;
+        let dir = TempDir::new("timestamping");
+        let path = dir.join("file.bin");
+        let downloader = Downloader::new(&format!("{url}/file.bin")).with_timestamping(true);
+
+        let downloaded = downloader.clone().download(dir.path()).await.unwrap();
+        assert_eq!(downloaded, 100_000);
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        assert_eq!(
+            Some(modified),
+            parse_http_date(crate::test_server::LAST_MODIFIED)
+        );
+        let meta = FileMeta::load(&FileMeta::meta_path(&path)).unwrap();
+        assert_eq!(meta.etag.as_deref(), Some(crate::test_server::ETAG));
+
+        // the second run only probes the server and asks whether the file changed
+        let requests = server.requests.load(Ordering::SeqCst);
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let downloaded = downloader
+            .with_events(sender)
+            .download(dir.path())
+            .await
+            .unwrap();
+        assert_eq!(downloaded, 0);
+        assert_eq!(server.requests.load(Ordering::SeqCst), requests + 2);
+        assert_eq!(
+            receiver.recv().await,
+            Some(DownloadEvent::NotModified { path: path.clone() })
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn test_download_not_found() {
+        let (url, _) = serve(Behaviour::NotFound, test_body(10)).await;
+        let dir = TempDir::new("not-found");
+        let result = Downloader::new(&format!("{url}/file.bin"))
+            .download(dir.path())
+            .await;
+        assert!(result.is_err());
+        assert!(!std::path::Path::new(&dir.join("file.bin")).exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_existing_file() {
+        let body = test_body(100_000);
+        let (url, _) = serve(Behaviour::Ranges, body.clone()).await;
+        let url = format!("{url}/file.bin");
+        let dir = TempDir::new("existing");
+        let path = dir.join("file.bin");
+        std::fs::write(&path, &body[..30_000]).unwrap();
+
+        let error = Downloader::new(&url)
+            .download(dir.path())
+            .await
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(error.as_ref()), ErrorKind::Filesystem);
+        assert_eq!(std::fs::read(&path).unwrap(), body[..30_000]);
+
+        let downloaded = Downloader::new(&url)
+            .with_existing_file(ExistingFile::Continue)
+            .download(dir.path())
+            .await
+            .unwrap();
+        assert_eq!(downloaded, 70_000);
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+
+        std::fs::write(&path, b"old").unwrap();
+        Downloader::new(&url)
+            .with_existing_file(ExistingFile::Overwrite)
+            .download(dir.path())
+            .await
+            .unwrap();
+        assert_downloaded(dir.path(), &body);
+    }
+
+    #[tokio::test]
+    async fn test_continue_without_ranges() {
+        let body = test_body(100_000);
+        let (url, _) = serve(Behaviour::CutOffOnce, body.clone()).await;
+        let dir = TempDir::new("continue-stream");
+        let path = dir.join("file.bin");
+        std::fs::write(&path, &body[..30_000]).unwrap();
+        let downloader = Downloader::new(&format!("{url}/file.bin"))
+            .with_existing_file(ExistingFile::Continue)
+            .with_retry_policy(RetryPolicy {
+                max_retries: 0,
+                delay: Duration::ZERO,
+            });
+
+        // the download starts over, the existing file is kept until it is done
+        assert!(downloader.clone().download(dir.path()).await.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), body[..30_000]);
+        downloader.clone().download(dir.path()).await.unwrap();
+        assert_downloaded(dir.path(), &body);
+    }
+
+    #[tokio::test]
+    async fn test_download_failure_keeps_part_file() {
+        let (url, _) = serve(Behaviour::FailsAfterProbe, test_body(100_000)).await;
+        let dir = TempDir::new("part");
+        let result = Downloader::new(&format!("{url}/file.bin"))
+            .with_retry_policy(RetryPolicy {
+                max_retries: 0,
+                delay: Duration::ZERO,
+            })
+            .download(dir.path())
+            .await;
+        assert!(result.is_err());
+        let path = dir.join("file.bin");
+        // the preallocated file never gets the final name
+        assert!(!std::path::Path::new(&path).exists());
+        assert!(std::path::Path::new(&DownloadState::part_path(&path)).exists());
+        assert!(std::path::Path::new(&DownloadState::sidecar_path(&path)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_from_ftp() {
+        // the first transfer is cut off, the retry continues with REST
+        let body = test_body(100_000);
+        let (url, server) = serve_ftp(body.clone(), true).await;
+        let dir = TempDir::new("ftp");
+        Downloader::new(&format!("{url}/file.bin"))
+            .with_connections(1)
+            .with_retry_policy(RetryPolicy {
+                max_retries: 1,
+                delay: Duration::from_millis(10),
+            })
+            .download(dir.path())
+            .await
+            .unwrap();
+        assert_eq!(*server.retrievals.lock().unwrap(), [0, 50_000]);
+        assert_downloaded(dir.path(), &body);
+    }
+
+    #[tokio::test]
+    async fn test_download_from_file_url() {
+        let body = test_body(100_000);
+        let dir = TempDir::new("file-url");
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/file.bin"), &body).unwrap();
+        let url = Url::from_file_path(dir.join("src/file.bin")).unwrap();
+        Downloader::new(url.as_str())
+            .with_connections(4)
+            .download(dir.path())
+            .await
+            .unwrap();
+        assert_downloaded(dir.path(), &body);
     }
 }
@@ -0,0 +1,181 @@
+use std::{error::Error, fmt::Display, io};
+
+use reqwest::StatusCode;
+
+use crate::retry::ChunkError;
+
+/// # HTTP Error
+/// The server answered with an unsuccessful status code.
+#[derive(Debug)]
+pub struct HttpError {
+    pub status: StatusCode,
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the server responded with {}", self.status)
+    }
+}
+
+impl Error for HttpError {}
+
+/// # FTP Error
+/// The FTP server answered a command with an error reply.
+#[derive(Debug)]
+pub struct FtpError {
+    pub code: u16,
+    pub message: String,
+}
+
+impl Display for FtpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the FTP server responded with {} {}",
+            self.code, self.message
+        )
+    }
+}
+
+impl Error for FtpError {}
+
+/// # Verification Error
+/// The downloaded file does not match the expected checksum, or the expected
+/// checksum can not be found.
+#[derive(Debug)]
+pub struct VerificationError(pub String);
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for VerificationError {}
+
+/// # Error Kind
+/// What went wrong with a download, so that scripts can react to the exit
+/// code of the command line without parsing the error message.
+///
+/// | Kind         | Exit code | Example                                |
+/// | ------------ | --------- | -------------------------------------- |
+/// | Other        | 1         | invalid url, a server that misbehaves  |
+/// | Filesystem   | 3         | the file can not be created or written |
+/// | Network      | 4         | timeouts, refused or reset connections |
+/// | Verification | 5         | checksum mismatch                      |
+/// | Http         | 8         | 404, 503 after all retries, FTP 550... |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Other,
+    Filesystem,
+    Network,
+    Verification,
+    Http,
+}
+
+impl ErrorKind {
+    /// Classifies an error returned by [`Downloader::download`](crate::Downloader::download).
+    pub fn of(error: &(dyn Error + 'static)) -> Self {
+        if let Some(error) = error.downcast_ref::<ChunkError>() {
+            return match error {
+                // retried errors without a known cause are network problems,
+                // e.g. a connection that was closed too early, and so are
+                // the I/O errors of a socket
+                ChunkError::Transient(e) => match Self::of(e.as_ref()) {
+                    Self::Other | Self::Filesystem => Self::Network,
+                    kind => kind,
+                },
+                ChunkError::Fatal(e) => Self::of(e.as_ref()),
+            };
+        }
+        if error.is::<HttpError>() || error.is::<FtpError>() {
+            return Self::Http;
+        }
+        if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            return match error.status() {
+                Some(_) => Self::Http,
+                None => Self::Network,
+            };
+        }
+        if error.is::<io::Error>() {
+            return Self::Filesystem;
+        }
+        if error.is::<VerificationError>() {
+            return Self::Verification;
+        }
+        match error.source() {
+            Some(source) => Self::of(source),
+            None => Self::Other,
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Other => 1,
+            Self::Filesystem => 3,
+            Self::Network => 4,
+            Self::Verification => 5,
+            Self::Http => 8,
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Other => write!(f, "other"),
+            Self::Filesystem => write!(f, "filesystem"),
+            Self::Network => write!(f, "network"),
+            Self::Verification => write!(f, "verification"),
+            Self::Http => write!(f, "http"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(error: impl Into<Box<dyn Error + Send + Sync>>) -> ErrorKind {
+        ErrorKind::of(error.into().as_ref())
+    }
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(
+            kind(ChunkError::from_status(StatusCode::NOT_FOUND)),
+            ErrorKind::Http
+        );
+        assert_eq!(
+            kind(ChunkError::from(io::Error::other("disk full"))),
+            ErrorKind::Filesystem
+        );
+        assert_eq!(
+            kind(ChunkError::transient("the connection was closed")),
+            ErrorKind::Network
+        );
+        assert_eq!(
+            kind(ChunkError::transient(io::Error::from(
+                io::ErrorKind::ConnectionRefused
+            ))),
+            ErrorKind::Network
+        );
+        assert_eq!(
+            kind(ChunkError::fatal(FtpError {
+                code: 550,
+                message: "No such file".to_owned()
+            })),
+            ErrorKind::Http
+        );
+        assert_eq!(
+            kind(ChunkError::fatal("the server ignored the Range request")),
+            ErrorKind::Other
+        );
+        assert_eq!(
+            kind(VerificationError("checksum mismatch".to_owned())),
+            ErrorKind::Verification
+        );
+        assert_eq!(kind("Task join error"), ErrorKind::Other);
+        assert_eq!(ErrorKind::Http.exit_code(), 8);
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use crate::error::ErrorKind;
+
+/// # Download Event
+/// Events emitted by a [`Downloader`](crate::Downloader) while it downloads a
+/// file. They are sent to the channel given to
+/// [`Downloader::with_events`](crate::Downloader::with_events), so that any
+/// user interface (or none at all) can show the progress.
+///
+/// A file that is downloaded in a single stream is reported as chunk `0` of
+/// connection `0`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadEvent {
+    /// the server was probed and the download starts
+    Started {
+        url: String,
+        /// path of the downloaded file
+        path: String,
+        file_size: Option<u64>,
+        /// whether the file is downloaded in multiple chunks
+        ranges: bool,
+        chunks: usize,
+        connections: usize,
+        /// number of servers the file is downloaded from
+        mirrors: usize,
+        /// bytes that were already downloaded by a previous run
+        resumed: u64,
+    },
+    /// a connection started to download the bytes `start..=end` of a chunk
+    ChunkStarted {
+        connection: usize,
+        chunk: usize,
+        start: u64,
+        end: u64,
+    },
+    /// bytes of a chunk were received
+    ChunkProgress {
+        connection: usize,
+        chunk: usize,
+        /// bytes of the chunk that were received so far
+        downloaded: u64,
+        /// current size of the chunk, it shrinks when another connection
+        /// takes over the end of the chunk
+        size: Option<u64>,
+    },
+    /// a chunk failed and will be downloaded again after `delay`
+    ChunkRetried {
+        connection: usize,
+        chunk: usize,
+        retry: u32,
+        max_retries: u32,
+        delay: Duration,
+        error: String,
+    },
+    /// the chunk is on disk, it may have been shortened by another connection
+    ChunkCompleted { connection: usize, chunk: usize },
+    /// the chunk failed after all retries, which fails the download
+    ChunkFailed {
+        connection: usize,
+        chunk: usize,
+        error: String,
+    },
+    /// a mirror is no longer used because it does not serve the same file,
+    /// failed or is much slower than the others
+    MirrorDropped { url: String, reason: String },
+    /// the existing file has not changed on the server, so it is kept
+    NotModified { path: String },
+    /// the downloaded file matches the expected checksum
+    Verified { checksum: String },
+    /// the download is complete, `bytes` were downloaded in this run
+    Completed { path: String, bytes: u64 },
+    /// the download failed, the error is also returned by `download`
+    Failed { error: String, kind: ErrorKind },
+}
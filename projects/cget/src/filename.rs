@@ -0,0 +1,240 @@
+/// # Parse Content-Disposition
+/// Extracts the file name from a `Content-Disposition` header value as
+/// described in RFC 6266.
+///
+/// The extended `filename*` parameter (RFC 5987) is preferred because it can
+/// carry non-ASCII names, for example `filename*=UTF-8''na%C3%AFve.pdf`. The
+/// plain `filename` parameter is used otherwise. The returned name is always
+/// sanitised into a safe base name.
+pub(crate) fn parse_content_disposition(value: &str) -> Option<String> {
+    let mut filename = None;
+    let mut extended = None;
+
+    for param in split_params(value).into_iter().skip(1) {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename*" => extended = decode_ext_value(value.trim()),
+            "filename" => filename = Some(unquote(value.trim())),
+            _ => {}
+        }
+    }
+    extended
+        .and_then(|name| sanitize_filename(&name))
+        .or_else(|| filename.and_then(|name| sanitize_filename(&name)))
+}
+
+/// splits the header value on `;` outside of quoted strings
+fn split_params(value: &str) -> Vec<String> {
+    let mut params = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    params.push(current);
+    params
+}
+
+/// removes the quotes of a quoted string and resolves the escaped characters
+fn unquote(value: &str) -> String {
+    let Some(inner) = value
+        .strip_prefix('"')
+        .map(|v| v.strip_suffix('"').unwrap_or(v))
+    else {
+        return value.to_owned();
+    };
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// decodes an RFC 5987 value such as `UTF-8'en'na%C3%AFve.pdf`
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?.to_ascii_lowercase();
+    let _language = parts.next()?;
+    let bytes = percent_decode(parts.next()?);
+    match charset.as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        // every ISO-8859-1 byte is the unicode code point with the same value
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
+}
+
+/// # Percent Decode
+/// Decodes `%XX` escape sequences. Invalid sequences are kept as they are.
+pub fn percent_decode(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes[i] {
+            b'%' => value
+                .get(i + 1..i + 3)
+                // `from_str_radix` would also take a sign, as in `%+1`
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    decoded
+}
+
+/// # Sanitize File Name
+/// Turns a name suggested by a server into a safe base name. Directories are
+/// stripped so that names such as `../../etc/passwd` can not escape the
+/// destination, control characters and characters that are not allowed on
+/// common file systems are replaced, and names that are empty or consist only
+/// of dots are rejected.
+pub fn sanitize_filename(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let sanitized: String = base
+        .chars()
+        .map(|c| match c {
+            c if c.is_control() => '_',
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim();
+    match sanitized.trim_matches('.').is_empty() {
+        true => None,
+        false => Some(sanitized.to_owned()),
+    }
+}
+
+/// # Guess Extension
+/// Guesses a file extension from the value of the `Content-Type` header.
+pub(crate) fn extension_from_content_type(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    let extension = match mime.as_str() {
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "text/css" => "css",
+        "text/csv" => "csv",
+        "text/javascript" | "application/javascript" => "js",
+        "application/json" => "json",
+        "application/xml" | "text/xml" => "xml",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/gzip" | "application/x-gzip" => "gz",
+        "application/x-tar" => "tar",
+        "application/x-bzip2" => "bz2",
+        "application/x-xz" => "xz",
+        "application/zstd" => "zst",
+        "application/x-7z-compressed" => "7z",
+        "application/vnd.rar" | "application/x-rar-compressed" => "rar",
+        "application/x-iso9660-image" => "iso",
+        "application/vnd.debian.binary-package" => "deb",
+        "application/x-rpm" => "rpm",
+        "application/x-msdownload" => "exe",
+        "application/wasm" => "wasm",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "audio/mpeg" => "mp3",
+        "audio/ogg" => "ogg",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        "application/octet-stream" => "bin",
+        _ => return None,
+    };
+    Some(extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_content_disposition() {
+        let parse = parse_content_disposition;
+        assert_eq!(
+            parse("attachment; filename=report.pdf"),
+            Some("report.pdf".to_owned())
+        );
+        assert_eq!(
+            parse("attachment; filename=\"annual report.pdf\"; size=1024"),
+            Some("annual report.pdf".to_owned())
+        );
+        assert_eq!(
+            parse("attachment; filename*=UTF-8''na%C3%AFve.pdf"),
+            Some("naïve.pdf".to_owned())
+        );
+        // the extended parameter is preferred wherever it appears
+        assert_eq!(
+            parse("attachment; filename*=utf-8'en'%E2%82%AC%20rates.txt; filename=\"rates.txt\""),
+            Some("€ rates.txt".to_owned())
+        );
+        assert_eq!(
+            parse("attachment; filename=\"semi;colon \\\"quoted\\\".txt\""),
+            Some("semi;colon _quoted_.txt".to_owned())
+        );
+        assert_eq!(
+            parse("attachment; filename*=iso-8859-1''caf%E9.txt"),
+            Some("café.txt".to_owned())
+        );
+        assert_eq!(parse("inline"), None);
+        assert_eq!(parse("attachment; filename=\"..\""), None);
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(
+            sanitize_filename("../../etc/passwd"),
+            Some("passwd".to_owned())
+        );
+        assert_eq!(
+            sanitize_filename("..\\..\\boot.ini"),
+            Some("boot.ini".to_owned())
+        );
+        assert_eq!(sanitize_filename("a\nb?.txt"), Some("a_b_.txt".to_owned()));
+        assert_eq!(sanitize_filename("dir/"), None);
+        assert_eq!(sanitize_filename("..."), None);
+    }
+
+    #[test]
+    fn test_extension_from_content_type() {
+        assert_eq!(
+            extension_from_content_type("application/pdf; charset=binary"),
+            Some("pdf")
+        );
+        assert_eq!(extension_from_content_type("IMAGE/PNG"), Some("png"));
+        assert_eq!(extension_from_content_type("application/x-unknown"), None);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b%zz%4"), b"a b%zz%4".to_vec());
+        assert_eq!(percent_decode("%+1%-1"), b"%+1%-1".to_vec());
+    }
+}
@@ -0,0 +1,306 @@
+use std::{collections::HashMap, time::Duration};
+
+use cget::{
+    DownloadEvent,
+    checksum::{self, Algorithm},
+    error::ErrorKind,
+};
+use serde_json::{Value, json};
+use tokio::{sync::mpsc::UnboundedReceiver, time::Instant};
+
+use crate::batch::BatchResult;
+
+/// minimum time between two progress lines of a download
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// # JSON Progress
+/// Turns the events of a download into the JSON objects of `--progress=json`.
+/// Every object has an `event` field with the kind of the event and the `url`
+/// of the download, e.g.
+///
+/// ```text
+/// {"event":"started","url":"https://example.com/a.iso","path":"./a.iso","size":1048576,...}
+/// {"event":"progress","url":"https://example.com/a.iso","downloaded":524288,"total":1048576,"speed":262144,"eta":2.0}
+/// {"event":"completed","url":"https://example.com/a.iso","path":"./a.iso","bytes":1048576,"duration":4.1,"sha256":"9f86..."}
+/// ```
+struct JsonProgress {
+    url: String,
+    started: Instant,
+    last_progress: Option<Instant>,
+    total: Option<u64>,
+    resumed: u64,
+    /// bytes of every chunk that are on disk
+    chunks: HashMap<usize, u64>,
+}
+
+impl JsonProgress {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            started: Instant::now(),
+            last_progress: None,
+            total: None,
+            resumed: 0,
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// returns the object of the event, or `None` if nothing is reported
+    /// (e.g. progress that comes too soon after the last one)
+    fn event(&mut self, event: DownloadEvent, now: Instant) -> Option<Value> {
+        let duration = now.duration_since(self.started).as_secs_f64();
+        let mut value = match event {
+            DownloadEvent::Started {
+                path,
+                file_size,
+                ranges,
+                chunks,
+                connections,
+                mirrors,
+                resumed,
+                ..
+            } => {
+                self.total = file_size;
+                self.resumed = resumed;
+                json!({
+                    "event": "started",
+                    "path": path,
+                    "size": file_size,
+                    "ranges": ranges,
+                    "chunks": chunks,
+                    "connections": connections,
+                    "mirrors": mirrors,
+                    "resumed": resumed,
+                })
+            }
+            DownloadEvent::ChunkStarted {
+                connection,
+                chunk,
+                start,
+                end,
+            } => json!({
+                "event": "chunk_started",
+                "connection": connection,
+                "chunk": chunk,
+                "start": start,
+                "end": end,
+            }),
+            DownloadEvent::ChunkProgress {
+                chunk, downloaded, ..
+            } => {
+                self.chunks.insert(chunk, downloaded);
+                if self
+                    .last_progress
+                    .is_some_and(|last| now.duration_since(last) < PROGRESS_INTERVAL)
+                {
+                    return None;
+                }
+                self.last_progress = Some(now);
+                let downloaded: u64 = self.chunks.values().sum();
+                let speed = if duration > 0.0 {
+                    downloaded.saturating_sub(self.resumed) as f64 / duration
+                } else {
+                    0.0
+                };
+                let eta = match self.total {
+                    Some(total) if speed > 0.0 => {
+                        Some(total.saturating_sub(downloaded) as f64 / speed)
+                    }
+                    _ => None,
+                };
+                json!({
+                    "event": "progress",
+                    "downloaded": downloaded,
+                    "total": self.total,
+                    "speed": speed as u64,
+                    "eta": eta,
+                })
+            }
+            DownloadEvent::ChunkRetried {
+                connection,
+                chunk,
+                retry,
+                max_retries,
+                delay,
+                error,
+            } => json!({
+                "event": "chunk_retried",
+                "connection": connection,
+                "chunk": chunk,
+                "retry": retry,
+                "max_retries": max_retries,
+                "delay": delay.as_secs_f64(),
+                "error": error,
+            }),
+            DownloadEvent::ChunkCompleted { connection, chunk } => json!({
+                "event": "chunk_completed",
+                "connection": connection,
+                "chunk": chunk,
+            }),
+            DownloadEvent::ChunkFailed {
+                connection,
+                chunk,
+                error,
+            } => json!({
+                "event": "chunk_failed",
+                "connection": connection,
+                "chunk": chunk,
+                "error": error,
+            }),
+            DownloadEvent::MirrorDropped { url, reason } => json!({
+                "event": "mirror_dropped",
+                "mirror": url,
+                "reason": reason,
+            }),
+            DownloadEvent::NotModified { path } => json!({
+                "event": "not_modified",
+                "path": path,
+            }),
+            DownloadEvent::Verified { checksum } => json!({
+                "event": "verified",
+                "checksum": checksum.to_string(),
+            }),
+            DownloadEvent::Completed { path, bytes } => json!({
+                "event": "completed",
+                "path": path,
+                "bytes": bytes,
+                "duration": duration,
+            }),
+            DownloadEvent::Failed { error, kind } => json!({
+                "event": "failed",
+                "error": error,
+                "kind": kind.to_string(),
+                "duration": duration,
+            }),
+        };
+        value["url"] = json!(self.url);
+        Some(value)
+    }
+}
+
+/// # Show JSON Progress
+/// Prints the events of a download as JSON lines. The completed event also
+/// carries the sha256 of the downloaded file.
+pub(crate) async fn show(url: String, mut events: UnboundedReceiver<DownloadEvent>) {
+    let mut progress = JsonProgress::new(url);
+    while let Some(event) = events.recv().await {
+        let Some(mut value) = progress.event(event, Instant::now()) else {
+            continue;
+        };
+        let completed = match value["event"] == "completed" {
+            true => value["path"].as_str().map(str::to_owned),
+            false => None,
+        };
+        if let Some(path) = completed {
+            let sha256 = tokio::task::spawn_blocking(move || {
+                checksum::hash_file(&path, Algorithm::Sha256, 0, None)
+            })
+            .await;
+            if let Ok(Ok(sha256)) = sha256 {
+                value["sha256"] = json!(sha256);
+            }
+        }
+        println!("{value}");
+    }
+}
+
+/// prints an error that prevents any download
+pub(crate) fn print_error(kind: ErrorKind, message: &str) {
+    println!(
+        "{}",
+        json!({
+            "event": "failed",
+            "error": message,
+            "kind": kind.to_string(),
+        })
+    );
+}
+
+/// prints the status of every download of the batch as a single line
+pub(crate) fn print_summary(results: &[BatchResult]) {
+    let downloads: Vec<Value> = results
+        .iter()
+        .map(|result| match &result.result {
+            Ok(bytes) => json!({ "url": result.url, "bytes": bytes }),
+            Err((kind, error)) => json!({
+                "url": result.url,
+                "error": error,
+                "kind": kind.to_string(),
+            }),
+        })
+        .collect();
+    let succeeded = results.iter().filter(|r| r.result.is_ok()).count();
+    println!(
+        "{}",
+        json!({
+            "event": "summary",
+            "succeeded": succeeded,
+            "failed": results.len() - succeeded,
+            "bytes": results.iter().filter_map(|r| r.result.as_ref().ok()).sum::<u64>(),
+            "downloads": downloads,
+        })
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_progress() {
+        let mut progress = JsonProgress::new("http://example.com/a.bin".to_owned());
+        let start = progress.started;
+        let started = progress
+            .event(
+                DownloadEvent::Started {
+                    url: "http://example.com/a.bin".to_owned(),
+                    path: "./a.bin".to_owned(),
+                    file_size: Some(1000),
+                    ranges: true,
+                    chunks: 2,
+                    connections: 2,
+                    mirrors: 1,
+                    resumed: 100,
+                },
+                start,
+            )
+            .unwrap();
+        assert_eq!(started["event"], "started");
+        assert_eq!(started["url"], "http://example.com/a.bin");
+        assert_eq!(started["size"], 1000);
+
+        let chunk = |chunk, downloaded| DownloadEvent::ChunkProgress {
+            connection: chunk,
+            chunk,
+            downloaded,
+            size: Some(500),
+        };
+        // the first chunk was resumed with 100 bytes on disk
+        progress.event(chunk(0, 100), start).unwrap();
+        // progress is only reported every PROGRESS_INTERVAL
+        assert_eq!(progress.event(chunk(1, 0), start), None);
+        let soon = start + Duration::from_millis(300);
+        assert_eq!(progress.event(chunk(0, 300), soon), None);
+        let value = progress
+            .event(chunk(1, 200), start + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(value["event"], "progress");
+        assert_eq!(value["downloaded"], 500);
+        assert_eq!(value["total"], 1000);
+        // 400 new bytes in 2 seconds
+        assert_eq!(value["speed"], 200);
+        assert_eq!(value["eta"], 2.5);
+
+        let failed = progress
+            .event(
+                DownloadEvent::Failed {
+                    error: "the server responded with 404 Not Found".to_owned(),
+                    kind: ErrorKind::Http,
+                },
+                start + Duration::from_secs(4),
+            )
+            .unwrap();
+        assert_eq!(failed["kind"], "http");
+        assert_eq!(failed["duration"], 4.0);
+    }
+}
@@ -0,0 +1,45 @@
+//! # cget
+//! A file downloader that splits files into chunks and downloads them over
+//! multiple connections. Downloads can be resumed, retried, throttled and
+//! verified against a checksum.
+//!
+//! The [`Downloader`] does not print anything. It reports its progress as
+//! [`DownloadEvent`]s, so that every tool can show them in its own way:
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+//! let mut downloader = cget::Downloader::new("https://example.com/file.iso")
+//!     .with_connections(4)
+//!     .with_events(sender);
+//! tokio::spawn(async move {
+//!     while let Some(event) = events.recv().await {
+//!         println!("{event:?}");
+//!     }
+//! });
+//! downloader.download(".").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod checksum;
+pub mod downloader;
+pub mod error;
+pub mod events;
+pub mod filename;
+mod mirror;
+mod probe;
+pub mod request;
+pub mod retry;
+mod source;
+mod state;
+pub mod strategy;
+#[cfg(test)]
+mod test_server;
+pub mod throttle;
+mod timestamp;
+pub mod units;
+
+pub use downloader::Downloader;
+pub use events::DownloadEvent;
+pub use reqwest::{Proxy, cookie::Jar, header};
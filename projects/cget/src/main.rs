@@ -1,39 +1,389 @@
 use clap::Parser;
 use regex::Regex;
-use std::process::exit;
+use std::{io::Read, process::exit, sync::Arc};
 
-mod downloader;
-use downloader::Downloader;
-use std::path::Path;
+mod batch;
+mod json;
+mod progress;
+mod recursive;
+use batch::BatchEntry;
+use cget::checksum::{Checksum, ChecksumSource};
+use cget::downloader::{DEFAULT_BUFFER_SIZE, Downloader, ExistingFile};
+use cget::error::ErrorKind;
+use cget::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use cget::retry::{DEFAULT_RETRIES, DEFAULT_RETRY_DELAY, RetryPolicy};
+use cget::strategy::{ChunkStrategy, DEFAULT_CONNECTIONS, DEFAULT_MIN_CHUNK_SIZE};
+use cget::throttle::{RateLimiter, Schedule};
+use cget::{Jar, Proxy, request, units};
+use progress::{Output, ProgressFormat};
+use std::{path::Path, time::Duration};
+use tokio::sync::Semaphore;
 
 #[derive(Debug, Parser)]
 #[command(version, about, long_about=None)]
 struct Args {
-    #[arg()]
-    url: String,
+    /// Url of the file to download. With --input-file, the first positional
+    /// argument is the destination instead
+    #[arg(required_unless_present = "input_file")]
+    url: Option<String>,
 
     #[arg(default_value_t = String::from("."))]
     dest: String,
+
+    /// Read urls from a file (or `-` for stdin), one per line, optionally
+    /// followed by the name of the output file
+    #[arg(short, long)]
+    input_file: Option<String>,
+
+    /// Crawl the directory listing or HTML page at the url and download the
+    /// linked files under it into the same directory tree
+    #[arg(short, long, conflicts_with_all = ["input_file", "checksum", "checksum_file", "mirrors"])]
+    recursive: bool,
+
+    /// Maximum number of levels below the url that are crawled
+    #[arg(short, long, default_value_t = recursive::DEFAULT_LEVEL, requires = "recursive")]
+    level: usize,
+
+    /// Only download files whose name matches one of these comma separated
+    /// patterns (e.g. '*.tar.gz,*.zip')
+    #[arg(short = 'A', long, value_delimiter = ',', requires = "recursive")]
+    accept: Vec<String>,
+
+    /// Maximum number of simultaneous connections across all files of the
+    /// input file or of the recursive download
+    #[arg(long, default_value_t = 16)]
+    max_connections: usize,
+
+    /// Maximum number of bytes every connection keeps in memory before
+    /// writing them to disk (e.g. 512K, 4M)
+    #[arg(long, value_parser = units::parse_size, default_value_t = DEFAULT_BUFFER_SIZE as u64)]
+    buffer_size: u64,
+
+    /// Number of simultaneous connections
+    #[arg(short, long, default_value_t = DEFAULT_CONNECTIONS)]
+    connections: usize,
+
+    /// Minimum size of a chunk (e.g. 256K, 1M)
+    #[arg(long, value_parser = units::parse_size, default_value_t = DEFAULT_MIN_CHUNK_SIZE)]
+    min_chunk_size: u64,
+
+    /// Maximum size of a chunk (e.g. 16M). If the file is split into more
+    /// chunks than connections, connections pick up the next chunk when done
+    #[arg(long, value_parser = units::parse_size)]
+    max_chunk_size: Option<u64>,
+
+    /// Let connections that finish early take over the remaining half of the
+    /// slowest chunk
+    #[arg(long)]
+    adaptive: bool,
+
+    /// Number of times a failed chunk is retried
+    #[arg(long, default_value_t = DEFAULT_RETRIES)]
+    retries: u32,
+
+    /// Seconds to wait before the first retry, it doubles on every retry
+    #[arg(long, default_value_t = DEFAULT_RETRY_DELAY.as_secs_f64())]
+    retry_delay: f64,
+
+    /// Expected checksum of the file as `<algorithm>:<hex>`, where algorithm
+    /// is one of sha256, sha1 or md5 (e.g. sha256:9f86d08...)
+    #[arg(long, conflicts_with = "input_file")]
+    checksum: Option<Checksum>,
+
+    /// Path or url of a checksum file (e.g. SHA256SUMS) that contains the
+    /// checksum of the downloaded file
+    #[arg(long, conflicts_with = "checksum")]
+    checksum_file: Option<String>,
+
+    /// Maximum total download speed in bytes per second across all
+    /// connections (e.g. 500K, 2M)
+    #[arg(long, value_parser = units::parse_size)]
+    limit_rate: Option<u64>,
+
+    /// Apply --limit-rate only during these local times and download at full
+    /// speed otherwise (e.g. "Mon-Fri 09:00-18:00, Sat 10:00-14:00")
+    #[arg(long, requires = "limit_rate")]
+    limit_schedule: Option<Schedule>,
+
+    /// Overwrite files that already exist
+    #[arg(short, long)]
+    force: bool,
+
+    /// Continue files that already exist, e.g. from an interrupted download
+    /// of another tool, by downloading only the rest of them
+    #[arg(long = "continue", conflicts_with = "force")]
+    continue_: bool,
+
+    /// Skip files that have not changed on the server since they were
+    /// downloaded, and set the modification time from Last-Modified
+    #[arg(short = 'N', long)]
+    timestamping: bool,
+
+    /// Another url that serves the same file, can be repeated. Chunks are
+    /// spread across the url and all mirrors
+    #[arg(long = "mirror", conflicts_with = "input_file")]
+    mirrors: Vec<String>,
+
+    /// Extra header to send with every request, can be repeated
+    /// (e.g. -H 'X-Api-Key: 1234')
+    #[arg(short = 'H', long = "header", value_parser = request::parse_header)]
+    headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// Basic authentication as `user:password`
+    #[arg(short, long)]
+    user: Option<String>,
+
+    /// Bearer token sent in the Authorization header
+    #[arg(long, conflicts_with = "user")]
+    bearer: Option<String>,
+
+    /// Netscape cookie file (e.g. exported by a browser or written by curl)
+    #[arg(long)]
+    cookies: Option<String>,
+
+    /// Proxy for all requests (e.g. http://proxy:3128, socks5://proxy:1080).
+    /// Without it, the HTTP_PROXY, HTTPS_PROXY and ALL_PROXY environment
+    /// variables are used
+    #[arg(long, value_parser = parse_proxy)]
+    proxy: Option<Proxy>,
+
+    /// How the progress is reported: progress bars, or one JSON object per
+    /// line with the events of the downloads
+    #[arg(long, value_enum, default_value_t = ProgressFormat::Bar)]
+    progress: ProgressFormat,
+
+    /// Do not print anything, the exit code tells whether the download
+    /// succeeded
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// checks the proxy url while the arguments are parsed
+fn parse_proxy(proxy: &str) -> Result<Proxy, String> {
+    Proxy::all(proxy).map_err(|e| format!("invalid proxy {proxy:?}: {e}"))
+}
+
+impl Args {
+    /// Collects the headers of every request: the extra headers and the
+    /// Authorization header
+    fn request_headers(&self) -> Result<HeaderMap, String> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.append(name.clone(), value.clone());
+        }
+        let authorization = match (&self.user, &self.bearer) {
+            (Some(user), _) => Some(request::basic_auth(user)?),
+            (_, Some(token)) => Some(request::bearer_auth(token)?),
+            _ => None,
+        };
+        if let Some(authorization) = authorization {
+            headers.insert(AUTHORIZATION, authorization);
+        }
+        Ok(headers)
+    }
+
+    /// Creates a downloader for the given url with the options of the command line
+    fn downloader(&self, url: &str, shared: &Shared) -> Downloader {
+        let checksum = match (&self.checksum, &self.checksum_file) {
+            (Some(checksum), _) => Some(ChecksumSource::Digest(checksum.clone())),
+            (_, Some(path)) => Some(ChecksumSource::SumsFile(path.clone())),
+            _ => None,
+        };
+        let strategy = ChunkStrategy {
+            connections: self.connections.max(1),
+            min_chunk_size: self.min_chunk_size,
+            max_chunk_size: self.max_chunk_size,
+            adaptive: self.adaptive,
+        };
+        Downloader::new(url)
+            .with_buffer_size(self.buffer_size as usize)
+            .with_strategy(strategy)
+            .with_retry_policy(RetryPolicy {
+                max_retries: self.retries,
+                delay: Duration::from_secs_f64(self.retry_delay.max(0.0)),
+            })
+            .with_checksum(checksum)
+            .with_rate_limit(shared.rate_limit.clone())
+            .with_headers(shared.headers.clone())
+            .with_cookies(shared.cookies.clone())
+            .with_proxy(self.proxy.clone())
+            .with_mirrors(self.mirrors.clone())
+            .with_timestamping(self.timestamping)
+            .with_existing_file(match (self.force, self.continue_) {
+                (true, _) => ExistingFile::Overwrite,
+                (_, true) => ExistingFile::Continue,
+                _ => ExistingFile::Refuse,
+            })
+    }
+}
+
+/// Settings that are prepared once and shared by all downloads
+struct Shared {
+    rate_limit: Option<Arc<RateLimiter>>,
+    headers: HeaderMap,
+    cookies: Option<Arc<Jar>>,
+}
+
+/// checks whether the url looks like a downloadable url
+fn is_valid_url(url: &str) -> bool {
+    // FIXME: add extensive url pattern matcher if it does not work on all cases
+    let re = Regex::new(r"(https?|ftp)://[^\s/$.?#].[^\s]*|file:///[^\s]+").unwrap();
+    re.is_match(url)
+}
+
+/// Downloads all entries at once with a shared connection limit, prints the
+/// summary and exits with the exit code of the first failed download
+async fn run_batch(
+    args: &Args,
+    shared: &Shared,
+    output: Output,
+    entries: Vec<BatchEntry>,
+    dest: &str,
+) {
+    let progress = output.multi_progress();
+    let connection_limit = Arc::new(Semaphore::new(args.max_connections.max(1)));
+    let results = batch::run(entries, dest, Arc::clone(&progress), output, |entry| {
+        if !is_valid_url(&entry.url) {
+            return Err("Invalid Url parameter provided".to_owned());
+        }
+        Ok(args
+            .downloader(&entry.url, shared)
+            .with_output_name(entry.output_name.clone())
+            .with_connection_limit(Arc::clone(&connection_limit)))
+    })
+    .await;
+
+    match output {
+        Output::Bar => batch::print_summary(&results),
+        Output::Json => json::print_summary(&results),
+        Output::Quiet => {}
+    }
+    if let Some(code) = batch::exit_code(&results) {
+        exit(code);
+    }
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
-    let path = Path::new(&args.dest);
+    let output = Output::new(args.progress, args.quiet);
+
+    // in batch mode, the only positional argument is the destination
+    let (url, dest) = match (&args.input_file, &args.url) {
+        (Some(_), Some(dest)) => (None, dest.clone()),
+        _ => (args.url.clone(), args.dest.clone()),
+    };
+    let path = Path::new(&dest);
     if !path.exists() {
-        println!("The destination path does not exist");
-        exit(1);
+        output.fail(ErrorKind::Filesystem, "The destination path does not exist");
     }
 
-    // FIXME: add extensive url pattern matcher if it does not work on all cases
-    let re = Regex::new(r"https?://[^\s/$.?#].[^\s]*").unwrap();
-    if let Some(_) = re.captures(&args.url) {
-        let mut downloader = Downloader::new(&args.url);
-        if let Ok(_) = downloader.download(&args.dest).await {
-            println!("Download Complete!")
+    let headers = match args.request_headers() {
+        Ok(headers) => headers,
+        Err(e) => output.fail(ErrorKind::Other, e),
+    };
+    let cookies = match args.cookies.as_deref().map(request::load_cookies) {
+        Some(Ok(jar)) => Some(Arc::new(jar)),
+        Some(Err(e)) => output.fail(
+            ErrorKind::Filesystem,
+            format!("Unable to read the cookie file: {e}"),
+        ),
+        None => None,
+    };
+    let shared = Shared {
+        // a single rate limiter is shared by every connection of every file
+        rate_limit: args
+            .limit_rate
+            .map(|rate| Arc::new(RateLimiter::new(rate, args.limit_schedule.clone()))),
+        headers,
+        cookies,
+    };
+
+    if args.recursive {
+        let url = url.unwrap_or_default();
+        if !is_valid_url(&url) {
+            output.fail(ErrorKind::Other, "Invalid Url parameter provided");
+        }
+        if !url.starts_with("http") {
+            output.fail(
+                ErrorKind::Other,
+                "Only http and https urls can be downloaded recursively",
+            );
+        }
+        let client = match args.downloader(&url, &shared).client() {
+            Ok(client) => client,
+            Err(e) => output.fail(ErrorKind::of(&e), e),
+        };
+        let entries = match recursive::crawl(&client, &url, args.level, &args.accept).await {
+            Ok(entries) => entries,
+            Err(e) => output.fail(
+                ErrorKind::of(e.as_ref()),
+                format!("Unable to crawl {url}: {e}"),
+            ),
+        };
+        output.println(format!("Found {} files", entries.len()));
+        let (entries, skipped) = recursive::skip_existing(&client, entries, &dest).await;
+        if skipped > 0 {
+            output.println(format!(
+                "Skipping {skipped} files that are already downloaded"
+            ));
+        }
+        if entries.is_empty() {
+            output.println("✅ Nothing to download");
+            return;
+        }
+        // the directory tree of the server is mirrored into the destination
+        for entry in &entries {
+            let path = Path::new(&dest).join(entry.output_name.as_deref().unwrap_or_default());
+            let Some(parent) = path.parent() else {
+                continue;
+            };
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                output.fail(
+                    ErrorKind::Filesystem,
+                    format!("Unable to create {}: {e}", parent.display()),
+                );
+            }
+        }
+        run_batch(&args, &shared, output, entries, &dest).await;
+        return;
+    }
+
+    if let Some(input_file) = &args.input_file {
+        let content = match input_file.as_str() {
+            "-" => {
+                let mut content = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut content)
+                    .map(|_| content)
+            }
+            path => std::fs::read_to_string(path),
+        };
+        let entries = match content {
+            Ok(content) => batch::parse_entries(&content),
+            Err(e) => output.fail(
+                ErrorKind::Filesystem,
+                format!("Unable to read the input file: {e}"),
+            ),
+        };
+
+        run_batch(&args, &shared, output, entries, &dest).await;
+        return;
+    }
+
+    let url = url.unwrap_or_default();
+    if is_valid_url(&url) {
+        let downloader = args.downloader(&url, &shared);
+        let progress = output.multi_progress();
+        match progress::download(downloader, &dest, progress, output).await {
+            Ok(_) => output.println("Download Complete!"),
+            Err(e) => {
+                // the json output already reported the failure as an event
+                output.println(format!("⛔ Download failed: {e}"));
+                exit(ErrorKind::of(e.as_ref()).exit_code());
+            }
         }
     } else {
-        println!("Invalid Url parameter provided");
-        exit(1);
+        output.fail(ErrorKind::Other, "Invalid Url parameter provided");
     }
 }
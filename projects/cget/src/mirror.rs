@@ -0,0 +1,137 @@
+/// a mirror is dropped when the fastest mirror is this many times faster
+pub(crate) const SLOW_MIRROR_RATIO: f64 = 4.0;
+/// weight of a new measurement in the average speed of a mirror
+const SPEED_SMOOTHING: f64 = 0.3;
+
+/// A server that serves the file
+#[derive(Debug, Clone)]
+struct Mirror {
+    url: String,
+    /// average speed of a connection to this mirror in bytes per second
+    speed: Option<f64>,
+    /// number of connections that are downloading from this mirror
+    connections: usize,
+    dropped: bool,
+}
+
+/// # Mirrors
+/// The servers that serve the same file. Chunks are spread across the mirrors
+/// by giving every new request to the mirror with the fewest connections.
+/// Mirrors that fail or are much slower than the others are dropped, so their
+/// chunks are picked up by the remaining mirrors. The last mirror is never
+/// dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct Mirrors {
+    mirrors: Vec<Mirror>,
+}
+
+impl Mirrors {
+    pub(crate) fn new(urls: Vec<String>) -> Self {
+        let mirrors = urls
+            .into_iter()
+            .map(|url| Mirror {
+                url,
+                speed: None,
+                connections: 0,
+                dropped: false,
+            })
+            .collect();
+        Self { mirrors }
+    }
+
+    pub(crate) fn url(&self, mirror: usize) -> &str {
+        &self.mirrors[mirror].url
+    }
+
+    /// number of mirrors that were not dropped
+    pub(crate) fn active(&self) -> usize {
+        self.mirrors.iter().filter(|m| !m.dropped).count()
+    }
+
+    pub(crate) fn is_dropped(&self, mirror: usize) -> bool {
+        self.mirrors[mirror].dropped
+    }
+
+    /// picks the mirror for a new request, it must be released afterwards
+    pub(crate) fn pick(&mut self) -> usize {
+        let mirror = (0..self.mirrors.len())
+            .filter(|&i| !self.mirrors[i].dropped)
+            .min_by_key(|&i| self.mirrors[i].connections)
+            .unwrap_or(0);
+        self.mirrors[mirror].connections += 1;
+        mirror
+    }
+
+    pub(crate) fn release(&mut self, mirror: usize) {
+        let mirror = &mut self.mirrors[mirror];
+        mirror.connections = mirror.connections.saturating_sub(1);
+    }
+
+    /// records the speed of a connection to the mirror
+    pub(crate) fn record_speed(&mut self, mirror: usize, speed: f64) {
+        let mirror = &mut self.mirrors[mirror];
+        mirror.speed = Some(match mirror.speed {
+            Some(average) => average + SPEED_SMOOTHING * (speed - average),
+            None => speed,
+        });
+    }
+
+    /// checks whether another mirror is much faster than the given mirror
+    pub(crate) fn is_too_slow(&self, mirror: usize) -> bool {
+        let Some(speed) = self.mirrors[mirror].speed else {
+            return false;
+        };
+        self.mirrors
+            .iter()
+            .enumerate()
+            .filter(|(i, m)| *i != mirror && !m.dropped)
+            .filter_map(|(_, m)| m.speed)
+            .any(|other| other > speed * SLOW_MIRROR_RATIO)
+    }
+
+    /// drops the mirror unless it is the last one, returns whether it was
+    /// dropped by this call
+    pub(crate) fn drop_mirror(&mut self, mirror: usize) -> bool {
+        if self.mirrors[mirror].dropped || self.active() <= 1 {
+            return false;
+        }
+        self.mirrors[mirror].dropped = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_spreads_connections() {
+        let mut mirrors = Mirrors::new(vec!["a".into(), "b".into(), "c".into()]);
+        let picked: Vec<usize> = (0..6).map(|_| mirrors.pick()).collect();
+        assert_eq!(picked, vec![0, 1, 2, 0, 1, 2]);
+        mirrors.release(1);
+        assert_eq!(mirrors.pick(), 1);
+
+        assert!(mirrors.drop_mirror(0));
+        assert!(!mirrors.drop_mirror(0));
+        assert!(mirrors.is_dropped(0));
+        assert_ne!(mirrors.pick(), 0);
+    }
+
+    #[test]
+    fn test_drop_slow_mirror() {
+        let mut mirrors = Mirrors::new(vec!["a".into(), "b".into()]);
+        assert!(!mirrors.is_too_slow(0));
+        mirrors.record_speed(0, 100.0);
+        mirrors.record_speed(1, 300.0);
+        assert!(!mirrors.is_too_slow(0));
+        mirrors.record_speed(1, 2000.0);
+        assert!(mirrors.is_too_slow(0));
+        assert!(!mirrors.is_too_slow(1));
+
+        // the last mirror is kept even when it is slow
+        assert!(mirrors.drop_mirror(0));
+        assert!(!mirrors.drop_mirror(1));
+        assert_eq!(mirrors.active(), 1);
+    }
+}
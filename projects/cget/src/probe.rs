@@ -0,0 +1,139 @@
+use reqwest::{
+    Client, StatusCode,
+    header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, HeaderMap, RANGE},
+};
+
+use crate::downloader::HeaderUtils;
+use crate::retry::ChunkError;
+
+/// # Server Info
+/// What the server tells about the file before it is downloaded.
+///
+/// - `headers`: headers of the response used to find the file name, etc.
+/// - `file_size`: size of the file, if the server tells it
+/// - `ranges`: whether the server answers `Range` requests with partial
+///   content, which is required to download the file in multiple chunks
+#[derive(Debug)]
+pub(crate) struct ServerInfo {
+    pub(crate) headers: HeaderMap,
+    pub(crate) file_size: Option<u64>,
+    pub(crate) ranges: bool,
+}
+
+/// # Probe
+/// Finds out the size of the file and whether the server supports ranges.
+///
+/// 1. A `HEAD` request provides the `Content-Length` and `Accept-Ranges`
+///    headers. Servers that do not allow `HEAD` are probed with `GET` only.
+/// 2. Unless the server says `Accept-Ranges: none`, a `GET` request for the
+///    first byte confirms the range support. A `206 Partial Content` response
+///    with a `Content-Range` proves it, while a `200 OK` response means the
+///    server ignores ranges and sends the whole body, which is not read.
+pub(crate) async fn probe(client: &Client, url: &str) -> Result<ServerInfo, ChunkError> {
+    let mut info = ServerInfo {
+        headers: HeaderMap::new(),
+        file_size: None,
+        ranges: false,
+    };
+
+    let head = client.head(url).send().await?;
+    if head.status().is_success() {
+        info.headers = head.headers().clone();
+        info.file_size = content_length(&info.headers);
+        let accept_ranges = info
+            .headers
+            .get(ACCEPT_RANGES)
+            .and_then(|v| v.to_str().ok());
+        if accept_ranges.is_some_and(|v| v.eq_ignore_ascii_case("none")) {
+            return Ok(info);
+        }
+    }
+
+    let response = client.get(url).header(RANGE, "bytes=0-0").send().await?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    // dropping the response closes the connection without reading the body
+    drop(response);
+
+    match status {
+        StatusCode::PARTIAL_CONTENT => {
+            if let Ok(file_size) = headers.extract_file_size() {
+                info.file_size = Some(file_size);
+                info.ranges = true;
+            }
+            // keep the headers of HEAD as they describe the whole file
+            if info.headers.is_empty() {
+                info.headers = headers;
+            }
+        }
+        // an empty file can not satisfy any range: `Content-Range: bytes */0`
+        StatusCode::RANGE_NOT_SATISFIABLE if headers.extract_file_size().ok() == Some(0) => {
+            info.file_size = Some(0);
+            info.headers = headers;
+        }
+        status if status.is_success() => {
+            info.file_size = content_length(&headers).or(info.file_size);
+            info.headers = headers;
+        }
+        status => return Err(ChunkError::from_status(status)),
+    }
+    Ok(info)
+}
+
+/// returns the value of the `Content-Length` header
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    // a content range describes a part of the file, not the file itself
+    if headers.contains_key(CONTENT_RANGE) {
+        return None;
+    }
+    headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Behaviour, serve, test_body};
+
+    async fn probe_server(behaviour: Behaviour, len: usize) -> ServerInfo {
+        let (url, _) = serve(behaviour, test_body(len)).await;
+        probe(&Client::new(), &format!("{url}/file.bin"))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_probe_ranges() {
+        let info = probe_server(Behaviour::Ranges, 1000).await;
+        assert_eq!((info.file_size, info.ranges), (Some(1000), true));
+        // the headers of HEAD are kept
+        assert!(!info.headers.contains_key(CONTENT_RANGE));
+
+        let info = probe_server(Behaviour::NoHead, 1000).await;
+        assert_eq!((info.file_size, info.ranges), (Some(1000), true));
+    }
+
+    #[tokio::test]
+    async fn test_probe_without_ranges() {
+        let info = probe_server(Behaviour::IgnoresRanges, 1000).await;
+        assert_eq!((info.file_size, info.ranges), (Some(1000), false));
+
+        let info = probe_server(Behaviour::AcceptRangesNone, 1000).await;
+        assert_eq!((info.file_size, info.ranges), (Some(1000), false));
+
+        let info = probe_server(Behaviour::NoContentLength, 1000).await;
+        assert_eq!((info.file_size, info.ranges), (None, false));
+    }
+
+    #[tokio::test]
+    async fn test_probe_empty_file() {
+        let info = probe_server(Behaviour::Ranges, 0).await;
+        assert_eq!((info.file_size, info.ranges), (Some(0), false));
+    }
+
+    #[tokio::test]
+    async fn test_probe_not_found() {
+        let (url, _) = serve(Behaviour::NotFound, test_body(10)).await;
+        let error = probe(&Client::new(), &url).await.unwrap_err();
+        assert!(!error.is_transient());
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    collections::HashMap, error::Error, fmt::Display, process::exit, sync::Arc, time::Duration,
+};
+
+use cget::{DownloadEvent, Downloader, error::ErrorKind};
+use clap::ValueEnum;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+use crate::json;
+
+/// format of `--progress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ProgressFormat {
+    /// progress bars and messages for humans
+    Bar,
+    /// one JSON object per line for scripts and CI logs
+    Json,
+}
+
+/// # Output
+/// How the command line reports the downloads: progress bars, JSON lines or
+/// nothing at all with `--quiet`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Output {
+    Bar,
+    Json,
+    Quiet,
+}
+
+impl Output {
+    pub(crate) fn new(format: ProgressFormat, quiet: bool) -> Self {
+        match (format, quiet) {
+            (_, true) => Self::Quiet,
+            (ProgressFormat::Bar, false) => Self::Bar,
+            (ProgressFormat::Json, false) => Self::Json,
+        }
+    }
+
+    /// returns where the progress bars are drawn, they are hidden unless the
+    /// output is meant for humans
+    pub(crate) fn multi_progress(&self) -> Arc<MultiProgress> {
+        match self {
+            Self::Bar => Arc::new(MultiProgress::new()),
+            _ => Arc::new(MultiProgress::with_draw_target(ProgressDrawTarget::hidden())),
+        }
+    }
+
+    /// prints a message for humans
+    pub(crate) fn println(&self, message: impl Display) {
+        if *self == Self::Bar {
+            println!("{message}");
+        }
+    }
+
+    /// reports an error that prevents any download and exits with the exit
+    /// code of its kind
+    pub(crate) fn fail(&self, kind: ErrorKind, message: impl Display) -> ! {
+        match self {
+            Self::Bar => println!("⛔ {message}"),
+            Self::Json => json::print_error(kind, &message.to_string()),
+            Self::Quiet => {}
+        }
+        exit(kind.exit_code())
+    }
+}
+
+/// # Download With Progress
+/// Downloads the file while the events of the downloader are reported in the
+/// given output. Progress bars are drawn into the given `MultiProgress`.
+pub(crate) async fn download(
+    downloader: Downloader,
+    dest: &str,
+    progress: Arc<MultiProgress>,
+    output: Output,
+) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let (sender, receiver) = unbounded_channel();
+    let ui = match output {
+        Output::Bar => tokio::spawn(show(receiver, progress)),
+        Output::Json => tokio::spawn(json::show(downloader.url().to_owned(), receiver)),
+        Output::Quiet => tokio::spawn(async move {
+            let mut receiver = receiver;
+            while receiver.recv().await.is_some() {}
+        }),
+    };
+    let mut downloader = downloader.with_events(sender);
+    let result = downloader.download(dest).await;
+    // the channel is closed once the downloader is gone
+    drop(downloader);
+    let _ = ui.await;
+    result
+}
+
+/// # Show Progress
+/// Turns the events of a single download into progress bars: one bar per
+/// connection, or a single bar (a spinner when the size is not known) when
+/// the file is downloaded in a single stream.
+async fn show(mut events: UnboundedReceiver<DownloadEvent>, progress: Arc<MultiProgress>) {
+    // prints a line without breaking the progress bars
+    let log = |message: String| progress.suspend(|| println!("{message}"));
+    let mut up_to_date = false;
+    let mut bars: HashMap<usize, ProgressBar> = HashMap::new();
+
+    while let Some(event) = events.recv().await {
+        match event {
+            DownloadEvent::Started {
+                path,
+                file_size,
+                ranges,
+                chunks,
+                connections,
+                mirrors,
+                resumed,
+                ..
+            } => {
+                let name = path.rsplit('/').next().unwrap_or(&path).to_owned();
+                log(format!("⛔filename: {name}"));
+                match file_size {
+                    Some(file_size) => log(format!("⛔file size: {}", HumanBytes(file_size))),
+                    None => log("⛔ Unable to determine the file size. skipping threads".into()),
+                }
+                if file_size.is_some() && !ranges {
+                    log(
+                        "⛔ The server does not support ranges. downloading in a single stream"
+                            .into(),
+                    );
+                }
+                if resumed > 0 {
+                    let remaining = file_size.unwrap_or_default().saturating_sub(resumed);
+                    log(format!(
+                        "⛔ Resuming download: {} remaining",
+                        HumanBytes(remaining)
+                    ));
+                }
+                if ranges {
+                    log(format!(
+                        "Created {chunks} chunks for download over {connections} connections"
+                    ));
+                    if mirrors > 1 {
+                        log(format!("Downloading from {mirrors} mirrors"));
+                    }
+                    for connection in 0..connections {
+                        let bar = progress.add(ProgressBar::new(0));
+                        bar.set_style(ProgressStyle::with_template(
+                            &format!("{{prefix}} [Conn {connection}] {{wide_bar:40.cyan/blue}} {{binary_bytes}}/{{binary_total_bytes}} ({{percent}}%) {{msg}}")
+                        ).unwrap());
+                        bar.set_prefix(name.clone());
+                        bars.insert(connection, bar);
+                    }
+                } else {
+                    bars.insert(0, single_stream_bar(&progress, &name, file_size));
+                }
+            }
+            DownloadEvent::ChunkStarted { .. } | DownloadEvent::ChunkCompleted { .. } => {}
+            DownloadEvent::ChunkFailed {
+                connection,
+                chunk,
+                error,
+            } => {
+                if let Some(bar) = bars.get(&connection) {
+                    bar.abandon_with_message(format!("chunk {chunk} failed: {error}"));
+                }
+            }
+            DownloadEvent::ChunkProgress {
+                connection,
+                chunk,
+                downloaded,
+                size,
+            } => {
+                if let Some(bar) = bars.get(&connection) {
+                    if let Some(size) = size {
+                        bar.set_length(size);
+                    }
+                    bar.set_position(downloaded);
+                    bar.set_message(format!("chunk {chunk}"));
+                }
+            }
+            DownloadEvent::ChunkRetried {
+                connection,
+                chunk,
+                retry,
+                max_retries,
+                delay,
+                error,
+            } => {
+                if let Some(bar) = bars.get(&connection) {
+                    bar.set_message(format!(
+                        "chunk {chunk} retry {retry}/{max_retries} in {:.1}s: {error}",
+                        delay.as_secs_f64()
+                    ));
+                }
+            }
+            DownloadEvent::MirrorDropped { url, reason } => {
+                log(format!("⛔ Dropped mirror {url}: {reason}"));
+            }
+            DownloadEvent::NotModified { path } => {
+                up_to_date = true;
+                log(format!("✅ {path} is up to date"));
+            }
+            DownloadEvent::Verified { checksum } => {
+                log(format!("✅ checksum verified ({checksum})"));
+            }
+            DownloadEvent::Completed { .. } if up_to_date => {}
+            DownloadEvent::Completed { bytes, .. } => {
+                for bar in bars.values() {
+                    bar.finish_and_clear();
+                }
+                log(format!(
+                    "Download completed! Total bytes: {}",
+                    HumanBytes(bytes)
+                ));
+            }
+            DownloadEvent::Failed { error, .. } => {
+                for bar in bars.values().filter(|bar| !bar.is_finished()) {
+                    bar.abandon_with_message(error.clone());
+                }
+            }
+        }
+    }
+}
+
+/// creates the progress bar of a download in a single stream
+fn single_stream_bar(progress: &MultiProgress, name: &str, file_size: Option<u64>) -> ProgressBar {
+    match file_size {
+        Some(file_size) => {
+            let bar = progress.add(ProgressBar::new(file_size));
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "{prefix} {wide_bar:40.cyan/blue} {binary_bytes}/{binary_total_bytes} ({percent}%)",
+                )
+                .unwrap(),
+            );
+            bar.set_prefix(name.to_owned());
+            bar
+        }
+        None => {
+            let bar = progress.add(ProgressBar::new_spinner());
+            bar.enable_steady_tick(Duration::from_millis(100));
+            bar.set_style(
+                ProgressStyle::with_template(&format!(
+                    "{{spinner:.cyan}} {name:?} ({{binary_bytes}} downloaded)"
+                ))
+                .unwrap()
+                // .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏"),
+                // set tick character as a moon's phase as progress indicator
+                .tick_chars("🌑🌒🌓🌔🌕🌖🌗🌘"),
+            );
+            bar
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    error::Error,
+    path::Path,
+};
+
+use cget::filename::{percent_decode, sanitize_filename};
+use futures_util::{StreamExt, stream};
+use regex::Regex;
+use reqwest::{
+    Client, Url,
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+};
+
+use crate::batch::BatchEntry;
+
+/// default number of levels below the starting url that are crawled
+pub(crate) const DEFAULT_LEVEL: usize = 5;
+/// number of simultaneous requests that check the size of existing files
+const SIZE_CHECKS: usize = 8;
+
+/// # Crawl
+/// Crawls an HTTP directory listing or HTML page and returns the files to
+/// download, each with its path relative to the starting url.
+///
+/// - only links under the starting url are followed, so parent directories
+///   and other sites are never visited
+/// - directories (links ending with `/`) and HTML pages are crawled up to
+///   `level` levels below the starting url
+/// - every other link is a file, which is kept when its name matches one of
+///   the `accept` patterns (or when there are no patterns)
+pub(crate) async fn crawl(
+    client: &Client,
+    start: &str,
+    level: usize,
+    accept: &[String],
+) -> Result<Vec<BatchEntry>, Box<dyn Error + Send + Sync>> {
+    let start = Url::parse(start)?;
+    let prefix = prefix(&start);
+    let mut visited = HashSet::from([start.clone()]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    let mut entries = Vec::new();
+
+    while let Some((page, depth)) = queue.pop_front() {
+        let response = client.get(page.clone()).send().await?.error_for_status()?;
+        let is_html = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/html"));
+        if !is_html {
+            // the starting url (or a link that looked like a page) is a file
+            if let Some(entry) = entry(&prefix, &page, accept) {
+                entries.push(entry);
+            }
+            continue;
+        }
+        // the final url matters after redirects, e.g. `/dir` to `/dir/`
+        let base = response.url().clone();
+        let html = response.text().await?;
+
+        for link in extract_links(&html, &base) {
+            if !link.as_str().starts_with(prefix.as_str()) || !visited.insert(link.clone()) {
+                continue;
+            }
+            if is_page(&link) {
+                if depth < level {
+                    queue.push_back((link, depth + 1));
+                }
+            } else if let Some(entry) = entry(&prefix, &link, accept) {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// returns the directory of the url, every crawled link must start with it
+fn prefix(url: &Url) -> Url {
+    let mut prefix = url.clone();
+    prefix.set_query(None);
+    prefix.set_fragment(None);
+    if !prefix.path().ends_with('/') {
+        let path = prefix.path();
+        let dir = path[..=path.rfind('/').unwrap_or(0)].to_owned();
+        prefix.set_path(&dir);
+    }
+    prefix
+}
+
+/// extracts the `href` and `src` links of an HTML page as absolute urls
+fn extract_links(html: &str, base: &Url) -> Vec<Url> {
+    let re = Regex::new(r#"(?i)\b(?:href|src)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap();
+    re.captures_iter(html)
+        .filter_map(|captures| {
+            let link = captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .or_else(|| captures.get(3))?
+                .as_str()
+                .replace("&amp;", "&");
+            let mut url = base.join(&link).ok()?;
+            url.set_fragment(None);
+            // sort links of directory listings such as `?C=M;O=A`
+            if url.query().is_some() && url.path().ends_with('/') {
+                return None;
+            }
+            Some(url)
+        })
+        .collect()
+}
+
+/// checks whether the link is a directory or an HTML page that is crawled
+fn is_page(url: &Url) -> bool {
+    let path = url.path().to_ascii_lowercase();
+    path.ends_with('/') || path.ends_with(".html") || path.ends_with(".htm")
+}
+
+/// creates the entry of a file when its name matches the patterns
+fn entry(prefix: &Url, url: &Url, accept: &[String]) -> Option<BatchEntry> {
+    let path = local_path(prefix, url)?;
+    let name = path.rsplit('/').next()?;
+    if !accept.is_empty() && !accept.iter().any(|pattern| glob_match(pattern, name)) {
+        return None;
+    }
+    Some(BatchEntry {
+        url: url.to_string(),
+        output_name: Some(path),
+    })
+}
+
+/// returns the path of the url relative to the prefix, every segment is
+/// decoded and sanitised so that the file stays inside the destination
+fn local_path(prefix: &Url, url: &Url) -> Option<String> {
+    let relative = url.path().strip_prefix(prefix.path())?;
+    let segments: Vec<String> = relative
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| sanitize_filename(&String::from_utf8_lossy(&percent_decode(segment))))
+        .collect::<Option<_>>()?;
+    match segments.is_empty() {
+        true => None,
+        false => Some(segments.join("/")),
+    }
+}
+
+/// # Glob Match
+/// Matches a name against a pattern where `*` matches any number of
+/// characters and `?` matches a single character, e.g. `*.tar.gz`.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // let the last `*` match one more character
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// # Skip Existing
+/// Removes the entries whose file already exists in the destination with the
+/// same size as on the server. Returns the remaining entries and the number
+/// of skipped files.
+pub(crate) async fn skip_existing(
+    client: &Client,
+    entries: Vec<BatchEntry>,
+    dest: &str,
+) -> (Vec<BatchEntry>, usize) {
+    let total = entries.len();
+    let remaining: Vec<BatchEntry> = stream::iter(entries)
+        .map(|entry| async move {
+            let path = Path::new(dest).join(entry.output_name.as_deref().unwrap_or_default());
+            let Ok(metadata) = tokio::fs::metadata(&path).await else {
+                return Some(entry);
+            };
+            let size = client
+                .head(&entry.url)
+                .send()
+                .await
+                .ok()
+                .filter(|response| response.status().is_success())
+                // `content_length` of a HEAD response is the empty body
+                .and_then(|response| {
+                    response
+                        .headers()
+                        .get(CONTENT_LENGTH)?
+                        .to_str()
+                        .ok()?
+                        .parse::<u64>()
+                        .ok()
+                });
+            match size == Some(metadata.len()) {
+                true => None,
+                false => Some(entry),
+            }
+        })
+        .buffered(SIZE_CHECKS)
+        .filter_map(|entry| async move { entry })
+        .collect()
+        .await;
+    let skipped = total - remaining.len();
+    (remaining, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.tar.gz", "app-1.0.tar.gz"));
+        assert!(!glob_match("*.tar.gz", "app-1.0.tar.gz.sig"));
+        assert!(glob_match("app-?.?.zip", "app-1.2.zip"));
+        assert!(glob_match("*-*-linux*", "tool-1.0-linux-x64"));
+        assert!(glob_match("*", "anything"));
+        assert!(!glob_match("*.zip", "zip"));
+    }
+
+    #[test]
+    fn test_extract_links() {
+        let base = Url::parse("http://example.com/pub/").unwrap();
+        let html = r#"<a href="?C=M;O=A">Name</a> <a href="../">Parent</a>
+            <a HREF='sub/'>sub/</a> <a href=app.tar.gz>app</a>
+            <img src="/pub/logo.png#top"> <a href="http://other.org/x">x</a>"#;
+        let links: Vec<String> = extract_links(html, &base)
+            .iter()
+            .map(|url| url.to_string())
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                "http://example.com/",
+                "http://example.com/pub/sub/",
+                "http://example.com/pub/app.tar.gz",
+                "http://example.com/pub/logo.png",
+                "http://other.org/x",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_local_path() {
+        let prefix = prefix(&Url::parse("http://example.com/pub/index.html").unwrap());
+        assert_eq!(prefix.as_str(), "http://example.com/pub/");
+        let url = Url::parse("http://example.com/pub/a%20b/c.tar.gz").unwrap();
+        assert_eq!(local_path(&prefix, &url), Some("a b/c.tar.gz".to_owned()));
+        let url = Url::parse("http://example.com/pub/..%2F..%2Fetc").unwrap();
+        assert_eq!(local_path(&prefix, &url), Some("etc".to_owned()));
+        let url = Url::parse("http://example.com/other/file").unwrap();
+        assert_eq!(local_path(&prefix, &url), None);
+
+        let entry = entry(&prefix, &url, &[]);
+        assert!(entry.is_none());
+        let url = Url::parse("http://example.com/pub/sub/app.zip").unwrap();
+        assert!(entry_matches(&prefix, &url, "*.zip"));
+        assert!(!entry_matches(&prefix, &url, "*.tar.gz"));
+    }
+
+    fn entry_matches(prefix: &Url, url: &Url, pattern: &str) -> bool {
+        entry(prefix, url, &[pattern.to_owned()]).is_some()
+    }
+}
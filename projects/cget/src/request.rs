@@ -0,0 +1,201 @@
+use std::{
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use reqwest::{
+    Url,
+    cookie::Jar,
+    header::{HeaderName, HeaderValue},
+};
+
+/// # Parse Header
+/// Parses a header written as `Name: value`, for example
+/// `X-Api-Key: 1234`.
+pub fn parse_header(header: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header {header:?}, expected `Name: value`"))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|e| format!("invalid header name {name:?}: {e}"))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|e| format!("invalid value of header {name}: {e}"))?;
+    Ok((name, value))
+}
+
+/// # Basic Auth
+/// Returns the value of the `Authorization` header for the given
+/// `user:password`. The password may be omitted.
+pub fn basic_auth(user: &str) -> Result<HeaderValue, String> {
+    let (user, password) = user.split_once(':').unwrap_or((user, ""));
+    let credentials = STANDARD.encode(format!("{user}:{password}"));
+    sensitive(format!("Basic {credentials}"))
+}
+
+/// # Bearer Auth
+/// Returns the value of the `Authorization` header for the given token.
+pub fn bearer_auth(token: &str) -> Result<HeaderValue, String> {
+    sensitive(format!("Bearer {}", token.trim()))
+}
+
+/// creates a header value that is never shown in debug output
+fn sensitive(value: String) -> Result<HeaderValue, String> {
+    let mut value = HeaderValue::from_str(&value).map_err(|_| "invalid credentials".to_owned())?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// A cookie of a Netscape cookie file
+#[derive(Debug, PartialEq)]
+struct NetscapeCookie {
+    domain: String,
+    include_subdomains: bool,
+    path: String,
+    secure: bool,
+    /// unix timestamp, `0` for session cookies
+    expires: u64,
+    name: String,
+    value: String,
+}
+
+impl NetscapeCookie {
+    /// returns the cookie as a `Set-Cookie` header value and the url it was
+    /// set by
+    fn to_set_cookie(&self) -> Option<(String, Url)> {
+        let host = self.domain.trim_start_matches('.');
+        let scheme = if self.secure { "https" } else { "http" };
+        let url = Url::parse(&format!("{scheme}://{host}{}", self.path)).ok()?;
+        let mut cookie = format!("{}={}; Path={}", self.name, self.value, self.path);
+        // cookies without a domain are only sent to the host that set them
+        if self.include_subdomains {
+            cookie.push_str(&format!("; Domain={host}"));
+        }
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        Some((cookie, url))
+    }
+}
+
+/// parses the content of a Netscape cookie file (as written by curl, wget or
+/// browser extensions), skipping comments and malformed lines
+fn parse_netscape_cookies(content: &str) -> Vec<NetscapeCookie> {
+    content
+        .lines()
+        .filter_map(|line| {
+            // curl marks HttpOnly cookies with a prefix on the domain
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [
+                domain,
+                include_subdomains,
+                path,
+                secure,
+                expires,
+                name,
+                value,
+            ] = fields[..]
+            else {
+                return None;
+            };
+            Some(NetscapeCookie {
+                domain: domain.to_owned(),
+                include_subdomains: include_subdomains.eq_ignore_ascii_case("TRUE"),
+                path: path.to_owned(),
+                secure: secure.eq_ignore_ascii_case("TRUE"),
+                expires: expires.parse().ok()?,
+                name: name.to_owned(),
+                value: value.trim_end_matches('\r').to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// # Load Cookies
+/// Reads a Netscape cookie file into a cookie jar. Expired cookies are
+/// skipped.
+pub fn load_cookies(path: &str) -> io::Result<Jar> {
+    let content = std::fs::read_to_string(path)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let jar = Jar::default();
+    for cookie in parse_netscape_cookies(&content) {
+        if cookie.expires != 0 && cookie.expires < now {
+            continue;
+        }
+        if let Some((cookie, url)) = cookie.to_set_cookie() {
+            jar.add_cookie_str(&cookie, &url);
+        }
+    }
+    Ok(jar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::TempDir;
+    use reqwest::cookie::CookieStore;
+
+    #[test]
+    fn test_parse_header() {
+        let (name, value) = parse_header("X-Api-Key:  secret ").unwrap();
+        assert_eq!(name.as_str(), "x-api-key");
+        assert_eq!(value, "secret");
+        assert!(parse_header("no colon").is_err());
+        assert!(parse_header("bad name: value").is_err());
+    }
+
+    #[test]
+    fn test_auth() {
+        assert_eq!(
+            basic_auth("aladdin:opensesame").unwrap(),
+            "Basic YWxhZGRpbjpvcGVuc2VzYW1l"
+        );
+        assert_eq!(basic_auth("user").unwrap(), "Basic dXNlcjo=");
+        let bearer = bearer_auth("token").unwrap();
+        assert_eq!(bearer, "Bearer token");
+        assert!(bearer.is_sensitive());
+    }
+
+    #[test]
+    fn test_load_cookies() {
+        let dir = TempDir::new("cookies");
+        let path = dir.join("cookies.txt");
+        std::fs::write(
+            &path,
+            "# Netscape HTTP Cookie File\n\
+             .example.com\tTRUE\t/\tFALSE\t0\tsession\tabc\n\
+             #HttpOnly_files.example.com\tFALSE\t/dl\tTRUE\t4102444800\ttoken\txyz\n\
+             example.com\tFALSE\t/\tFALSE\t1\texpired\told\n\
+             malformed line\n",
+        )
+        .unwrap();
+        let jar = load_cookies(&path).unwrap();
+
+        // the jar returns the cookies in no particular order
+        let cookies = |url: &str| {
+            let value = jar
+                .cookies(&Url::parse(url).unwrap())
+                .map(|value| value.to_str().unwrap().to_owned())
+                .unwrap_or_default();
+            let mut cookies: Vec<&str> = value.split("; ").collect();
+            cookies.sort();
+            cookies.join("; ")
+        };
+        assert_eq!(
+            cookies("https://files.example.com/dl/app.iso"),
+            "session=abc; token=xyz"
+        );
+        assert_eq!(
+            cookies("http://files.example.com/dl/app.iso"),
+            "session=abc"
+        );
+        assert_eq!(cookies("https://other.org/"), "");
+    }
+}
@@ -0,0 +1,159 @@
+use std::{error::Error, fmt::Display, io, time::Duration};
+
+use rand::Rng;
+
+use crate::error::HttpError;
+use reqwest::StatusCode;
+
+/// default number of retries of every chunk
+pub const DEFAULT_RETRIES: u32 = 5;
+/// default delay before the first retry
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// the delay between retries never grows beyond this
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// # Chunk Error
+/// Errors while downloading a chunk are classified so that only the errors
+/// that may go away on their own are retried.
+///
+/// - `Transient`: timeouts, connection resets, 5xx responses, etc.
+/// - `Fatal`: 404, 416, a server that ignores the `Range` header, a file that
+///   can not be written, etc.
+#[derive(Debug)]
+pub(crate) enum ChunkError {
+    Transient(Box<dyn Error + Send + Sync>),
+    Fatal(Box<dyn Error + Send + Sync>),
+}
+
+impl ChunkError {
+    pub(crate) fn transient(message: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self::Transient(message.into())
+    }
+
+    pub(crate) fn fatal(message: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self::Fatal(message.into())
+    }
+
+    /// Classifies an unsuccessful HTTP status code.
+    ///
+    /// Server errors, `408 Request Timeout` and `429 Too Many Requests` are
+    /// transient, every other client error is fatal.
+    pub(crate) fn from_status(status: StatusCode) -> Self {
+        let error = HttpError { status };
+        match status {
+            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => Self::transient(error),
+            status if status.is_server_error() => Self::transient(error),
+            _ => Self::fatal(error),
+        }
+    }
+
+    pub(crate) fn is_transient(&self) -> bool {
+        matches!(self, Self::Transient(_))
+    }
+}
+
+impl Display for ChunkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Transient(e) => write!(f, "{e}"),
+            Self::Fatal(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for ChunkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Transient(e) => Some(e.as_ref()),
+            Self::Fatal(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ChunkError {
+    fn from(error: reqwest::Error) -> Self {
+        if let Some(status) = error.status() {
+            return Self::from_status(status);
+        }
+        // timeouts, refused or reset connections and bodies that are cut off
+        // are network problems that are worth retrying, while invalid requests
+        // and undecodable content fail the same way every time. A body that is
+        // cut off while it is decoded is reported as a decode error, so the
+        // cause decides.
+        let cut_off = std::iter::successors(error.source(), |&e| e.source()).any(|e| {
+            e.downcast_ref::<reqwest::Error>()
+                .is_some_and(|e| e.is_body() || e.is_timeout())
+        });
+        if error.is_timeout() || error.is_connect() || error.is_body() || cut_off {
+            return Self::Transient(error.into());
+        }
+        Self::Fatal(error.into())
+    }
+}
+
+impl From<io::Error> for ChunkError {
+    fn from(error: io::Error) -> Self {
+        Self::Fatal(error.into())
+    }
+}
+
+/// # Retry Policy
+/// A failed chunk is retried up to `max_retries` times. The delay between the
+/// retries grows exponentially from `delay`, and a random jitter is added so
+/// that the connections do not hit the server at the same time again.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_RETRIES,
+            delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// returns how long to wait before the given retry (starting from 1)
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self
+            .delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(MAX_RETRY_DELAY);
+        // wait at least half of the exponential delay, the rest is random
+        let half = exponential / 2;
+        half + half.mul_f64(rand::rng().random_range(0.0..=1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        assert!(ChunkError::from_status(StatusCode::SERVICE_UNAVAILABLE).is_transient());
+        assert!(ChunkError::from_status(StatusCode::BAD_GATEWAY).is_transient());
+        assert!(ChunkError::from_status(StatusCode::TOO_MANY_REQUESTS).is_transient());
+        assert!(!ChunkError::from_status(StatusCode::NOT_FOUND).is_transient());
+        assert!(!ChunkError::from_status(StatusCode::RANGE_NOT_SATISFIABLE).is_transient());
+        assert!(!ChunkError::from(io::Error::other("disk full")).is_transient());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            delay: Duration::from_secs(1),
+        };
+        for (retry, max) in [(1, 1), (2, 2), (3, 4), (4, 8)] {
+            let delay = policy.backoff(retry);
+            assert!(delay >= Duration::from_secs(max) / 2);
+            assert!(delay <= Duration::from_secs(max));
+        }
+        assert!(policy.backoff(30) <= MAX_RETRY_DELAY);
+    }
+}
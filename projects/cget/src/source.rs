@@ -0,0 +1,103 @@
+//! # Sources
+//! The protocols a file can be downloaded with. The downloader only asks a
+//! [`Source`] for the metadata of the file and for the bytes of a range, so
+//! the chunks, retries, mirrors and resume work the same for every protocol.
+
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use futures_util::{future::BoxFuture, stream::BoxStream};
+use reqwest::{Client, Url};
+
+use crate::retry::ChunkError;
+use crate::timestamp::parse_http_date;
+
+mod file;
+mod ftp;
+mod http;
+
+pub(crate) use file::FileSource;
+pub(crate) use ftp::FtpSource;
+pub(crate) use http::HttpSource;
+
+/// the bytes of a file as they are received
+pub(crate) type ByteStream = BoxStream<'static, Result<Bytes, ChunkError>>;
+
+/// # Metadata
+/// What the source tells about the file before it is downloaded.
+///
+/// - `file_size`: size of the file, if it is known
+/// - `ranges`: whether the file can be read from any offset, which is
+///   required to download it in multiple chunks
+/// - `filename`: name of the file suggested by the server
+/// - `extension`: extension that matches the type of the file
+/// - `etag`: identifies the version of the file
+/// - `last_modified`: modification time of the file as an HTTP date
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Metadata {
+    pub(crate) file_size: Option<u64>,
+    pub(crate) ranges: bool,
+    pub(crate) filename: Option<String>,
+    pub(crate) extension: Option<&'static str>,
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+}
+
+/// # Source
+/// A protocol that files can be downloaded with.
+pub(crate) trait Source: Send + Sync + Debug {
+    /// finds out the size of the file, whether it can be read in ranges and
+    /// its name and version
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Metadata, ChunkError>>;
+
+    /// reads the bytes of the given range (both ends are included), or the
+    /// whole file without a range
+    fn read<'a>(
+        &'a self,
+        url: &'a str,
+        range: Option<(u64, u64)>,
+    ) -> BoxFuture<'a, Result<ByteStream, ChunkError>>;
+
+    /// # Not Modified
+    /// Tells whether the file has not changed since the version with the
+    /// given `ETag` or modification time (an HTTP date) was downloaded, or
+    /// `None` when that can not be told.
+    ///
+    /// By default the modification time of the file is compared, sources
+    /// that can ask the server directly override it.
+    fn not_modified<'a>(
+        &'a self,
+        url: &'a str,
+        _etag: Option<&'a str>,
+        modified_since: Option<&'a str>,
+    ) -> BoxFuture<'a, Option<bool>> {
+        Box::pin(async move {
+            let modified_since = parse_http_date(modified_since?)?;
+            let metadata = self.metadata(url).await.ok()?;
+            let modified = parse_http_date(metadata.last_modified.as_deref()?)?;
+            Some(modified <= modified_since)
+        })
+    }
+}
+
+/// # Source For
+/// Returns the source that downloads the given url, picked by its scheme:
+/// `http` and `https` use the given client, `file` reads a local file and
+/// `ftp` connects with the given timeouts.
+pub(crate) fn source_for(
+    url: &str,
+    client: &Client,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+) -> Result<Arc<dyn Source>, ChunkError> {
+    let parsed =
+        Url::parse(url).map_err(|e| ChunkError::fatal(format!("invalid url {url}: {e}")))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(Arc::new(HttpSource::new(client.clone()))),
+        "file" => Ok(Arc::new(FileSource)),
+        "ftp" => Ok(Arc::new(FtpSource::new(connect_timeout, read_timeout))),
+        scheme => Err(ChunkError::fatal(format!(
+            "the url scheme {scheme} is not supported"
+        ))),
+    }
+}
@@ -0,0 +1,113 @@
+use std::{io::SeekFrom, path::PathBuf};
+
+use bytes::Bytes;
+use futures_util::{StreamExt, future::BoxFuture, stream};
+use reqwest::Url;
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt},
+};
+
+use super::{ByteStream, Metadata, Source};
+use crate::retry::ChunkError;
+use crate::timestamp::format_http_date;
+
+/// number of bytes that are read from a local file at once
+const READ_SIZE: usize = 64 << 10;
+
+/// # File Source
+/// Copies a local file from a `file://` url, e.g. from a mounted network
+/// share.
+#[derive(Debug, Clone)]
+pub(crate) struct FileSource;
+
+/// returns the local path of a `file://` url
+fn path(url: &str) -> Result<PathBuf, ChunkError> {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| ChunkError::fatal(format!("{url} is not a local file")))
+}
+
+/// reads the reader until its end into a stream of bytes
+fn read_stream(reader: impl AsyncRead + Send + Unpin + 'static) -> ByteStream {
+    stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut buffer = vec![0; READ_SIZE];
+        match reader.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(len) => {
+                buffer.truncate(len);
+                Some((Ok(Bytes::from(buffer)), Some(reader)))
+            }
+            // the stream ends after an error
+            Err(e) => Some((Err(ChunkError::from(e)), None)),
+        }
+    })
+    .boxed()
+}
+
+impl Source for FileSource {
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Metadata, ChunkError>> {
+        Box::pin(async move {
+            let path = path(url)?;
+            let metadata = tokio::fs::metadata(&path).await?;
+            if !metadata.is_file() {
+                return Err(ChunkError::fatal(format!(
+                    "{} is not a file",
+                    path.display()
+                )));
+            }
+            Ok(Metadata {
+                file_size: Some(metadata.len()),
+                // an empty file has no range to read
+                ranges: metadata.len() > 0,
+                last_modified: metadata.modified().ok().map(format_http_date),
+                ..Metadata::default()
+            })
+        })
+    }
+
+    fn read<'a>(
+        &'a self,
+        url: &'a str,
+        range: Option<(u64, u64)>,
+    ) -> BoxFuture<'a, Result<ByteStream, ChunkError>> {
+        Box::pin(async move {
+            let mut file = File::open(path(url)?).await?;
+            Ok(match range {
+                Some((start, end)) => {
+                    file.seek(SeekFrom::Start(start)).await?;
+                    read_stream(file.take(end + 1 - start))
+                }
+                None => read_stream(file),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::TempDir;
+
+    #[tokio::test]
+    async fn test_file_source() {
+        let dir = TempDir::new("file-source");
+        let path = dir.join("file.bin");
+        std::fs::write(&path, b"0123456789").unwrap();
+        let url = Url::from_file_path(&path).unwrap().to_string();
+
+        let metadata = FileSource.metadata(&url).await.unwrap();
+        assert_eq!((metadata.file_size, metadata.ranges), (Some(10), true));
+        assert!(metadata.last_modified.is_some());
+
+        let stream = FileSource.read(&url, Some((2, 5))).await.unwrap();
+        let bytes: Vec<u8> = stream.map(|bytes| bytes.unwrap().to_vec()).concat().await;
+        assert_eq!(bytes, b"2345");
+
+        std::fs::remove_file(&path).unwrap();
+        let error = FileSource.metadata(&url).await.unwrap_err();
+        assert!(!error.is_transient());
+    }
+}
@@ -0,0 +1,101 @@
+use futures_util::{StreamExt, future::BoxFuture};
+use reqwest::{
+    Client, StatusCode,
+    header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE},
+};
+
+use super::{ByteStream, Metadata, Source};
+use crate::downloader::HeaderUtils;
+use crate::probe::probe;
+use crate::retry::ChunkError;
+
+/// # HTTP Source
+/// Downloads files over HTTP and HTTPS with `Range` requests.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpSource {
+    client: Client,
+}
+
+impl HttpSource {
+    pub(crate) fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+/// returns the value of the `ETag` header
+fn etag(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(|etag| etag.to_owned())
+}
+
+impl Source for HttpSource {
+    fn metadata<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Metadata, ChunkError>> {
+        Box::pin(async move {
+            let info = probe(&self.client, url).await?;
+            Ok(Metadata {
+                file_size: info.file_size,
+                ranges: info.ranges,
+                filename: info.headers.extract_filename().ok(),
+                extension: info.headers.guess_extension(),
+                etag: etag(&info.headers),
+                last_modified: info
+                    .headers
+                    .get(LAST_MODIFIED)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_owned()),
+            })
+        })
+    }
+
+    fn read<'a>(
+        &'a self,
+        url: &'a str,
+        range: Option<(u64, u64)>,
+    ) -> BoxFuture<'a, Result<ByteStream, ChunkError>> {
+        Box::pin(async move {
+            let mut builder = self.client.get(url);
+            if let Some((start, end)) = range {
+                builder = builder.header(RANGE, &format!("bytes={start}-{end}"));
+            }
+            let response = builder.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(ChunkError::from_status(status));
+            }
+            if range.is_some() && status != StatusCode::PARTIAL_CONTENT {
+                return Err(ChunkError::fatal("the server ignored the Range request"));
+            }
+            Ok(response
+                .bytes_stream()
+                .map(|bytes| bytes.map_err(ChunkError::from))
+                .boxed())
+        })
+    }
+
+    /// Sends the `ETag` in `If-None-Match` and the modification time in
+    /// `If-Modified-Since`, and `304 Not Modified` means that the file has
+    /// not changed, while a successful response means that it has.
+    fn not_modified<'a>(
+        &'a self,
+        url: &'a str,
+        etag: Option<&'a str>,
+        modified_since: Option<&'a str>,
+    ) -> BoxFuture<'a, Option<bool>> {
+        Box::pin(async move {
+            let mut request = self.client.get(url).header(RANGE, "bytes=0-0");
+            if let Some(etag) = etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(modified_since) = modified_since {
+                request = request.header(IF_MODIFIED_SINCE, modified_since);
+            }
+            match request.send().await.ok()?.status() {
+                StatusCode::NOT_MODIFIED => Some(true),
+                status if status.is_success() => Some(false),
+                _ => None,
+            }
+        })
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::downloader::Chunk;
+
+/// # Download State
+/// The download state is persisted next to the downloaded file as a sidecar
+/// file `<file>.cget`. It stores the size and the `ETag` of the remote file
+/// along with the progress of every chunk so that an interrupted download can
+/// be resumed by requesting only the missing byte ranges.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct DownloadState {
+    pub(crate) url: String,
+    pub(crate) file_size: u64,
+    pub(crate) etag: Option<String>,
+    pub(crate) chunks: Vec<Chunk>,
+}
+
+impl DownloadState {
+    /// returns the path of the sidecar file for the given download path
+    pub(crate) fn sidecar_path(filename: &str) -> String {
+        format!("{filename}.cget")
+    }
+
+    /// returns the path the file is downloaded into, it is renamed to the
+    /// given download path once the download is complete
+    pub(crate) fn part_path(filename: &str) -> String {
+        format!("{filename}.part")
+    }
+
+    /// Loads the state from the sidecar file if it exists and can be parsed.
+    pub(crate) fn load(path: &str) -> Option<Self> {
+        let data = fs::read(path).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Saves the state into the sidecar file.
+    pub(crate) fn save(&self, path: &str) -> io::Result<()> {
+        write_atomic(path, &serde_json::to_vec(self)?)
+    }
+
+    /// Removes the sidecar file once the download is complete.
+    pub(crate) fn remove(path: &str) {
+        let _ = fs::remove_file(path);
+    }
+
+    /// Checks whether the saved state still describes the remote file.
+    ///
+    /// The url and the file size must always match. The `ETag` is compared
+    /// only when both the saved state and the server provide one.
+    pub(crate) fn matches(&self, url: &str, file_size: u64, etag: Option<&str>) -> bool {
+        if self.url != url || self.file_size != file_size {
+            return false;
+        }
+        match (self.etag.as_deref(), etag) {
+            (Some(saved), Some(current)) => saved == current,
+            _ => true,
+        }
+    }
+}
+
+/// # Write Atomic
+/// Writes the data into a temporary file and renames it to the given path, so
+/// that a crash while saving never leaves a truncated file behind. The file
+/// and the directory are synced so that the rename survives a power loss.
+fn write_atomic(path: &str, data: &[u8]) -> io::Result<()> {
+    let tmp_path = format!("{path}.tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    // directories can not be opened as files on windows
+    #[cfg(unix)]
+    {
+        let dir = match std::path::Path::new(path).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => std::path::Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// # File Metadata
+/// The validators of a completely downloaded file. With timestamping they are
+/// saved next to the file as `<file>.cget-meta`, so that the next run can ask
+/// the server whether the file has changed since.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FileMeta {
+    pub(crate) url: String,
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+}
+
+impl FileMeta {
+    /// returns the path of the metadata file for the given download path
+    pub(crate) fn meta_path(filename: &str) -> String {
+        format!("{filename}.cget-meta")
+    }
+
+    pub(crate) fn load(path: &str) -> Option<Self> {
+        let data = fs::read(path).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    pub(crate) fn save(&self, path: &str) -> io::Result<()> {
+        write_atomic(path, &serde_json::to_vec(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::TempDir;
+
+    fn state() -> DownloadState {
+        let mut chunk = Chunk::new(0, 99);
+        chunk.downloaded = 40;
+        DownloadState {
+            url: "https://example.com/file.bin".to_owned(),
+            file_size: 200,
+            etag: Some("\"abc\"".to_owned()),
+            chunks: vec![chunk, Chunk::new(100, 199)],
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = TempDir::new("state");
+        let path = DownloadState::sidecar_path(&dir.join("file.bin"));
+        state().save(&path).unwrap();
+        let loaded = DownloadState::load(&path).unwrap();
+        DownloadState::remove(&path);
+
+        assert_eq!(loaded.file_size, 200);
+        assert_eq!(loaded.chunks.len(), 2);
+        assert_eq!(loaded.chunks[0].downloaded, 40);
+        assert_eq!(loaded.chunks[1].start_byte, 100);
+        assert!(DownloadState::load(&path).is_none());
+    }
+
+    #[test]
+    fn test_matches() {
+        let state = state();
+        let url = "https://example.com/file.bin";
+        assert!(state.matches(url, 200, Some("\"abc\"")));
+        assert!(state.matches(url, 200, None));
+        assert!(!state.matches(url, 201, Some("\"abc\"")));
+        assert!(!state.matches(url, 200, Some("\"def\"")));
+        assert!(!state.matches("https://example.com/other.bin", 200, None));
+    }
+
+    #[test]
+    fn test_file_meta() {
+        let dir = TempDir::new("meta");
+        let meta = FileMeta {
+            url: "https://example.com/file.bin".to_owned(),
+            etag: Some("\"abc\"".to_owned()),
+            last_modified: None,
+        };
+        let meta_path = FileMeta::meta_path(&dir.join("file.bin"));
+        meta.save(&meta_path).unwrap();
+        assert_eq!(FileMeta::load(&meta_path), Some(meta));
+    }
+}
@@ -0,0 +1,202 @@
+use std::time::Instant;
+
+use crate::downloader::{Chunk, ChunkStatus};
+
+/// default number of simultaneous connections
+pub const DEFAULT_CONNECTIONS: usize = 8;
+/// default minimum size of a chunk, smaller files use less connections
+pub const DEFAULT_MIN_CHUNK_SIZE: u64 = 256 << 10;
+
+/// # Chunk Strategy
+/// Decides how a file is split into chunks and how many connections download
+/// those chunks at the same time.
+///
+/// - `connections`: number of chunks that are downloaded simultaneously
+/// - `min_chunk_size`: a chunk is never smaller than this size
+/// - `max_chunk_size`: a chunk is never larger than this size. If the file is
+///   split into more chunks than connections, the connections take the next
+///   pending chunk when they are done with the current one.
+/// - `adaptive`: when there are no pending chunks left, a connection takes over
+///   the remaining half of the slowest active chunk (work stealing) so that one
+///   slow connection can not hold up the end of the download.
+#[derive(Debug, Clone)]
+pub struct ChunkStrategy {
+    pub connections: usize,
+    pub min_chunk_size: u64,
+    pub max_chunk_size: Option<u64>,
+    pub adaptive: bool,
+}
+
+impl Default for ChunkStrategy {
+    fn default() -> Self {
+        Self {
+            connections: DEFAULT_CONNECTIONS,
+            min_chunk_size: DEFAULT_MIN_CHUNK_SIZE,
+            max_chunk_size: None,
+            adaptive: false,
+        }
+    }
+}
+
+impl ChunkStrategy {
+    /// Splits a file of the given size into chunks.
+    ///
+    /// The file is divided evenly between the connections, and then the chunk
+    /// size is kept within the minimum and maximum chunk size.
+    pub(crate) fn split(&self, file_size: u64) -> Vec<Chunk> {
+        let mut chunk_size = file_size.div_ceil(self.connections.max(1) as u64);
+        chunk_size = chunk_size.max(self.min_chunk_size);
+        if let Some(max_chunk_size) = self.max_chunk_size {
+            chunk_size = chunk_size.min(max_chunk_size);
+        }
+        let chunk_size = chunk_size.max(1);
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < file_size {
+            let end = (start + chunk_size - 1).min(file_size - 1);
+            chunks.push(Chunk::new(start, end));
+            start = end + 1;
+        }
+        chunks
+    }
+
+    /// Picks the next chunk a free connection should download.
+    ///
+    /// Pending chunks are taken first. In adaptive mode, the remaining half of
+    /// the slowest active chunk is split off into a new chunk once there are no
+    /// pending chunks left. The picked chunk is marked as active.
+    pub(crate) fn next_chunk(&self, chunks: &mut Vec<Chunk>) -> Option<usize> {
+        let now = Instant::now();
+        let index = match chunks
+            .iter()
+            .position(|chunk| matches!(chunk.status, ChunkStatus::Pending) && !chunk.is_complete())
+        {
+            Some(index) => index,
+            None if self.adaptive => self.steal(chunks, now)?,
+            None => return None,
+        };
+        chunks[index].status = ChunkStatus::Active {
+            since: now,
+            downloaded: chunks[index].downloaded,
+        };
+        Some(index)
+    }
+
+    /// # Work Stealing
+    /// Finds the active chunk that is expected to finish last and moves the
+    /// second half of its remaining bytes into a new pending chunk.
+    ///
+    /// The expected time is estimated from the speed of the chunk since it was
+    /// started. Chunks that have not received anything yet are the slowest.
+    fn steal(&self, chunks: &mut Vec<Chunk>, now: Instant) -> Option<usize> {
+        let (victim, _) = chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, chunk)| {
+                let ChunkStatus::Active { since, downloaded } = chunk.status else {
+                    return None;
+                };
+                let remaining = chunk.remaining();
+                // splitting is only worth it when both halves are large enough
+                if remaining / 2 < self.min_chunk_size.max(1) {
+                    return None;
+                }
+                let progress = chunk.downloaded.saturating_sub(downloaded);
+                let time_left = match progress {
+                    0 => f64::INFINITY,
+                    _ => {
+                        remaining as f64 * now.duration_since(since).as_secs_f64() / progress as f64
+                    }
+                };
+                Some((index, time_left))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+        let chunk = &mut chunks[victim];
+        let middle = chunk.start_byte + chunk.downloaded + chunk.remaining() / 2;
+        let stolen = Chunk::new(middle, chunk.end_byte);
+        chunk.end_byte = middle - 1;
+        chunks.push(stolen);
+        Some(chunks.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let strategy = ChunkStrategy {
+            connections: 4,
+            min_chunk_size: 1,
+            max_chunk_size: None,
+            adaptive: false,
+        };
+        let chunks = strategy.split(1000);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!((chunks[0].start_byte, chunks[0].end_byte), (0, 249));
+        assert_eq!((chunks[3].start_byte, chunks[3].end_byte), (750, 999));
+
+        // chunks are never smaller than the minimum chunk size
+        let strategy = ChunkStrategy {
+            min_chunk_size: 400,
+            ..strategy
+        };
+        let chunks = strategy.split(1000);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].end_byte, 999);
+
+        // chunks are never larger than the maximum chunk size
+        let strategy = ChunkStrategy {
+            min_chunk_size: 1,
+            max_chunk_size: Some(100),
+            ..strategy
+        };
+        assert_eq!(strategy.split(1000).len(), 10);
+        assert_eq!(strategy.split(1).len(), 1);
+        assert!(strategy.split(0).is_empty());
+    }
+
+    #[test]
+    fn test_next_chunk() {
+        let strategy = ChunkStrategy {
+            connections: 2,
+            min_chunk_size: 1,
+            max_chunk_size: None,
+            adaptive: false,
+        };
+        let mut chunks = strategy.split(150);
+        // completed chunks are skipped
+        chunks[0].downloaded = 75;
+        assert_eq!(strategy.next_chunk(&mut chunks), Some(1));
+        // active chunks are not picked twice without work stealing
+        assert_eq!(strategy.next_chunk(&mut chunks), None);
+    }
+
+    #[test]
+    fn test_work_stealing() {
+        let strategy = ChunkStrategy {
+            connections: 2,
+            min_chunk_size: 10,
+            max_chunk_size: None,
+            adaptive: true,
+        };
+        let mut chunks = strategy.split(200);
+        assert_eq!(strategy.next_chunk(&mut chunks), Some(0));
+        assert_eq!(strategy.next_chunk(&mut chunks), Some(1));
+
+        // the first chunk is almost done, the second one has not started yet
+        chunks[0].downloaded = 90;
+        assert_eq!(strategy.next_chunk(&mut chunks), Some(2));
+        assert_eq!((chunks[1].start_byte, chunks[1].end_byte), (100, 149));
+        assert_eq!((chunks[2].start_byte, chunks[2].end_byte), (150, 199));
+
+        // remaining halves smaller than the minimum chunk size are not split
+        chunks
+            .iter_mut()
+            .for_each(|chunk| chunk.downloaded = chunk.size() - 10);
+        assert_eq!(strategy.next_chunk(&mut chunks), None);
+    }
+}
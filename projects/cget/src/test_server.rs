@@ -0,0 +1,312 @@
+//! A local stand-in for the servers `cget` downloads from, used by the tests.
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Method, Response, StatusCode, header},
+};
+use futures_util::StreamExt;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+/// `ETag` of the served file
+pub(crate) const ETAG: &str = "\"test\"";
+/// `Last-Modified` of the served file
+pub(crate) const LAST_MODIFIED: &str = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+/// How the stand-in server answers the requests
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Behaviour {
+    /// answers `Range` requests with `206 Partial Content`
+    Ranges,
+    /// like `Ranges`, but `HEAD` requests are not allowed
+    NoHead,
+    /// ignores the `Range` header and always sends the whole file
+    IgnoresRanges,
+    /// says `Accept-Ranges: none` and always sends the whole file
+    AcceptRangesNone,
+    /// sends the whole file chunked, without a `Content-Length`
+    NoContentLength,
+    /// like `AcceptRangesNone`, but the first response is cut off in the middle
+    CutOffOnce,
+    /// answers every request with `404 Not Found`
+    NotFound,
+    /// like `Ranges` for the probe, but every other range request fails with
+    /// `503 Service Unavailable`
+    FailsAfterProbe,
+}
+
+pub(crate) struct Server {
+    behaviour: Behaviour,
+    body: Vec<u8>,
+    /// number of `GET` requests
+    pub(crate) requests: AtomicUsize,
+    /// number of requests with an `Authorization` header
+    pub(crate) authorized: AtomicUsize,
+}
+
+/// # Temp Dir
+/// A new directory below the temp directory of the system for a test. It is
+/// removed with everything in it when it is dropped.
+pub(crate) struct TempDir(String);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("cget-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path.to_string_lossy().to_string())
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        &self.0
+    }
+
+    /// returns the path of the given file in the directory
+    pub(crate) fn join(&self, name: &str) -> String {
+        format!("{}/{name}", self.0)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// returns the content of the file served by the stand-in server
+pub(crate) fn test_body(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+/// Starts a stand-in server on a random local port and returns its url.
+pub(crate) async fn serve(behaviour: Behaviour, body: Vec<u8>) -> (String, Arc<Server>) {
+    let server = Arc::new(Server {
+        behaviour,
+        body,
+        requests: AtomicUsize::new(0),
+        authorized: AtomicUsize::new(0),
+    });
+    let app = Router::new()
+        .fallback(handler)
+        .with_state(Arc::clone(&server));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}"), server)
+}
+
+pub(crate) struct FtpServer {
+    body: Vec<u8>,
+    /// the offset of every `RETR` command, set by the `REST` before it
+    pub(crate) retrievals: Mutex<Vec<u64>>,
+    /// the first transfer is cut off in the middle
+    cut_first: AtomicBool,
+}
+
+/// Starts a stand-in FTP server on a random local port that serves
+/// `file.bin`, and returns its url.
+pub(crate) async fn serve_ftp(body: Vec<u8>, cut_first: bool) -> (String, Arc<FtpServer>) {
+    let server = Arc::new(FtpServer {
+        body,
+        retrievals: Mutex::new(Vec::new()),
+        cut_first: AtomicBool::new(cut_first),
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let state = Arc::clone(&server);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(ftp_session(Arc::clone(&state), stream));
+        }
+    });
+    (format!("ftp://{addr}"), server)
+}
+
+/// answers the commands of a control connection
+async fn ftp_session(server: Arc<FtpServer>, stream: TcpStream) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    writer.write_all(b"220 cget test server\r\n").await?;
+    let mut offset = 0;
+    let mut passive = None;
+    while let Some(line) = lines.next_line().await? {
+        let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+        let reply = match command {
+            "USER" => "331 Password required".to_owned(),
+            "PASS" => "230 Logged in".to_owned(),
+            "TYPE" => "200 Type set".to_owned(),
+            "SIZE" | "MDTM" | "RETR" if argument != "file.bin" => "550 No such file".to_owned(),
+            "SIZE" => format!("213 {}", server.body.len()),
+            "MDTM" => "213 19941106084937".to_owned(),
+            "REST" => {
+                offset = argument.parse().unwrap_or(0);
+                format!("350 Restarting at {offset}")
+            }
+            "PASV" => {
+                let listener = TcpListener::bind("127.0.0.1:0").await?;
+                let port = listener.local_addr()?.port();
+                passive = Some(listener);
+                format!(
+                    "227 Entering Passive Mode (127,0,0,1,{},{})",
+                    port >> 8,
+                    port & 255
+                )
+            }
+            "RETR" => match passive.take() {
+                Some(listener) => {
+                    writer.write_all(b"150 Opening data connection\r\n").await?;
+                    server.retrievals.lock().unwrap().push(offset);
+                    let (mut data, _) = listener.accept().await?;
+                    let mut body = &server.body[offset as usize..];
+                    let cut = server.cut_first.swap(false, Ordering::SeqCst);
+                    if cut {
+                        body = &body[..body.len() / 2];
+                    }
+                    // the client closes the data connection at the end of its range
+                    let _ = data.write_all(body).await;
+                    offset = 0;
+                    match cut {
+                        true => "426 Transfer aborted".to_owned(),
+                        false => "226 Transfer complete".to_owned(),
+                    }
+                }
+                None => "425 Use PASV first".to_owned(),
+            },
+            "QUIT" => {
+                writer.write_all(b"221 Bye\r\n").await?;
+                return Ok(());
+            }
+            _ => "502 Command not implemented".to_owned(),
+        };
+        writer.write_all(format!("{reply}\r\n").as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// parses a `bytes=<start>-<end>` range header
+fn parse_range(headers: &HeaderMap, len: usize) -> Option<(usize, usize)> {
+    let range = headers.get(header::RANGE)?.to_str().ok()?;
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let start: usize = start.parse().ok()?;
+    let end = match end {
+        "" => len - 1,
+        end => end.parse::<usize>().ok()?.min(len - 1),
+    };
+    Some((start, end))
+}
+
+async fn handler(
+    State(server): State<Arc<Server>>,
+    method: Method,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let body = &server.body;
+    let response = Response::builder();
+    if method == Method::GET {
+        server.requests.fetch_add(1, Ordering::SeqCst);
+    }
+    if headers.contains_key(header::AUTHORIZATION) {
+        server.authorized.fetch_add(1, Ordering::SeqCst);
+    }
+
+    // the file never changes, so it is not modified for a client that has it
+    if server.behaviour != Behaviour::NotFound
+        && headers
+            .get(header::IF_NONE_MATCH)
+            .is_some_and(|etag| etag == ETAG)
+    {
+        return response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let response = match server.behaviour {
+        Behaviour::NotFound => {
+            return response
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .unwrap();
+        }
+        Behaviour::NoHead if method == Method::HEAD => {
+            return response
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .body(Body::empty())
+                .unwrap();
+        }
+        Behaviour::FailsAfterProbe
+            if headers
+                .get(header::RANGE)
+                .is_some_and(|range| range != "bytes=0-0") =>
+        {
+            return response
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(Body::empty())
+                .unwrap();
+        }
+        Behaviour::Ranges | Behaviour::NoHead | Behaviour::FailsAfterProbe => {
+            let response = response.header(header::ACCEPT_RANGES, "bytes");
+            if headers.contains_key(header::RANGE) && body.is_empty() {
+                return response
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, "bytes */0")
+                    .body(Body::empty())
+                    .unwrap();
+            }
+            if let Some((start, end)) = parse_range(&headers, body.len()) {
+                return response
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(
+                        header::CONTENT_RANGE,
+                        format!("bytes {start}-{end}/{}", body.len()),
+                    )
+                    .body(Body::from(body[start..=end].to_vec()))
+                    .unwrap();
+            }
+            response
+        }
+        Behaviour::IgnoresRanges => response,
+        Behaviour::AcceptRangesNone => response.header(header::ACCEPT_RANGES, "none"),
+        Behaviour::CutOffOnce if server.requests.load(Ordering::SeqCst) == 1 => {
+            let chunks: Vec<Result<Vec<u8>, std::io::Error>> = vec![
+                Ok(body[..body.len() / 2].to_vec()),
+                Err(std::io::ErrorKind::ConnectionReset.into()),
+            ];
+            // the headers and the first half are sent before the connection breaks
+            let chunks = futures_util::stream::iter(chunks).then(|chunk| async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                chunk
+            });
+            return response
+                .header(header::ACCEPT_RANGES, "none")
+                .header(header::CONTENT_LENGTH, body.len())
+                .body(Body::from_stream(chunks))
+                .unwrap();
+        }
+        Behaviour::CutOffOnce => response.header(header::ACCEPT_RANGES, "none"),
+        Behaviour::NoContentLength => {
+            let chunks: Vec<Result<Vec<u8>, std::io::Error>> =
+                body.chunks(4096).map(|chunk| Ok(chunk.to_vec())).collect();
+            return response
+                .header(header::CONTENT_TYPE, "application/pdf")
+                .body(Body::from_stream(futures_util::stream::iter(chunks)))
+                .unwrap();
+        }
+    };
+    response
+        .header(header::ETAG, ETAG)
+        .header(header::LAST_MODIFIED, LAST_MODIFIED)
+        .body(Body::from(body.clone()))
+        .unwrap()
+}
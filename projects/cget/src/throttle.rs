@@ -0,0 +1,198 @@
+use std::{
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, Weekday};
+
+/// # Schedule Window
+/// A time window such as `09:00-18:00`, optionally limited to some days of the
+/// week such as `Mon-Fri 09:00-18:00`. A window whose end is before its start
+/// ends on the next day, e.g. `22:00-06:00`.
+#[derive(Debug, Clone, PartialEq)]
+struct Window {
+    days: Option<(Weekday, Weekday)>,
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl Window {
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        // a window that passes midnight belongs to the day it starts on
+        let (in_window, weekday) = match self.start <= self.end {
+            true => (self.start <= time && time < self.end, now.weekday()),
+            false if time >= self.start => (true, now.weekday()),
+            false => (time < self.end, now.weekday().pred()),
+        };
+        in_window
+            && match self.days {
+                Some((first, last)) => {
+                    let (first, last, day) = (
+                        first.num_days_from_monday(),
+                        last.num_days_from_monday(),
+                        weekday.num_days_from_monday(),
+                    );
+                    match first <= last {
+                        true => first <= day && day <= last,
+                        // e.g. `Sat-Mon`
+                        false => day >= first || day <= last,
+                    }
+                }
+                None => true,
+            }
+    }
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid schedule window: {s:?}");
+        let (days, hours) = match s.trim().split_once(char::is_whitespace) {
+            Some((days, hours)) => {
+                let (first, last) = days.split_once('-').unwrap_or((days, days));
+                let first: Weekday = first.parse().map_err(|_| invalid())?;
+                let last: Weekday = last.parse().map_err(|_| invalid())?;
+                (Some((first, last)), hours.trim())
+            }
+            None => (None, s.trim()),
+        };
+        let (start, end) = hours.split_once('-').ok_or_else(invalid)?;
+        let start = NaiveTime::parse_from_str(start, "%H:%M").map_err(|_| invalid())?;
+        let end = NaiveTime::parse_from_str(end, "%H:%M").map_err(|_| invalid())?;
+        Ok(Self { days, start, end })
+    }
+}
+
+/// # Schedule
+/// Comma separated time windows in local time during which the rate limit
+/// applies, e.g. `Mon-Fri 09:00-18:00, Sat 10:00-14:00`. Outside of them,
+/// files are downloaded at full speed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    windows: Vec<Window>,
+}
+
+impl Schedule {
+    /// checks whether the rate limit applies at the given local time
+    fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.windows.iter().any(|window| window.contains(now))
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let windows = s
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { windows })
+    }
+}
+
+/// tokens of the bucket, one per byte
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// # Rate Limiter
+/// A token bucket shared by all connections so that their total throughput
+/// stays below `rate` bytes per second.
+///
+/// The bucket holds at most one second worth of bytes. Every connection takes
+/// as many tokens as the bytes it received, and the bucket may go into debt,
+/// in which case the connection waits until the debt is paid back. Waiting
+/// before reading more data lets TCP slow down the sender.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: u64,
+    schedule: Option<Schedule>,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: u64, schedule: Option<Schedule>) -> Self {
+        let rate = rate.max(1);
+        Self {
+            rate,
+            schedule,
+            bucket: Mutex::new(Bucket {
+                tokens: rate as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// waits until `bytes` bytes may be received
+    pub(crate) async fn acquire(&self, bytes: usize) {
+        if let Some(schedule) = &self.schedule {
+            if !schedule.is_active(Local::now().naive_local()) {
+                return;
+            }
+        }
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.last_refill).as_secs_f64() * self.rate as f64;
+            bucket.tokens = (bucket.tokens + refill).min(self.rate as f64) - bytes as f64;
+            bucket.last_refill = now;
+            match bucket.tokens < 0.0 {
+                true => Duration::from_secs_f64(-bucket.tokens / self.rate as f64),
+                false => Duration::ZERO,
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, time: &str) -> NaiveDateTime {
+        // 2024-01-01 is a Monday
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    }
+
+    #[test]
+    fn test_schedule() {
+        let schedule: Schedule = "Mon-Fri 09:00-18:00".parse().unwrap();
+        assert!(schedule.is_active(at(1, "09:00")));
+        assert!(schedule.is_active(at(5, "17:59")));
+        assert!(!schedule.is_active(at(5, "18:00")));
+        assert!(!schedule.is_active(at(3, "02:00")));
+        assert!(!schedule.is_active(at(6, "12:00")));
+
+        let schedule: Schedule = "Fri 22:00-06:00, Sun 10:00-12:00".parse().unwrap();
+        assert!(schedule.is_active(at(5, "23:00")));
+        // the night of friday ends on saturday
+        assert!(schedule.is_active(at(6, "05:00")));
+        assert!(!schedule.is_active(at(5, "05:00")));
+        assert!(schedule.is_active(at(7, "11:00")));
+
+        assert!("09:00".parse::<Schedule>().is_err());
+        assert!("Someday 09:00-10:00".parse::<Schedule>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let limiter = RateLimiter::new(1 << 20, None);
+        let start = Instant::now();
+        // the first second worth of bytes is available right away
+        limiter.acquire(1 << 20).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+        limiter.acquire(256 << 10).await;
+        assert!(start.elapsed() >= Duration::from_millis(240));
+    }
+}
@@ -0,0 +1,33 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+
+/// # HTTP Date
+/// Formats a time as an HTTP date such as `Sun, 06 Nov 1994 08:49:37 GMT`, as
+/// used by the `If-Modified-Since` header.
+pub(crate) fn format_http_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+/// parses an HTTP date such as the value of the `Last-Modified` header
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(SystemTime::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_http_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(format_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(time));
+        assert_eq!(parse_http_date("yesterday"), None);
+    }
+}
@@ -0,0 +1,439 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use sha2::{Digest, Sha256};
+
+/// how long the terminal has to answer a query of the clipboard
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// how long the terminal is read after a query, so that an answer that
+/// comes too late is not typed into the prompt
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// # Clipboard
+/// Somewhere a secret can be copied to instead of printing it.
+pub(crate) trait Clipboard {
+    /// puts the value into the clipboard
+    fn copy(&mut self, value: &str) -> Result<(), String>;
+
+    /// returns what the clipboard holds, or `None` if it can not be read
+    fn contents(&mut self) -> Result<Option<String>, String>;
+
+    fn clear(&mut self) -> Result<(), String>;
+}
+
+/// what `clear_if_unchanged` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ClearOutcome {
+    Cleared,
+    /// something else has been copied since, which is kept
+    Changed,
+    /// the clipboard can not be read, so it is left alone
+    Unreadable,
+}
+
+/// # Copied
+/// A value that was copied by `LastCopy::copy`. Only its digest is kept, to
+/// tell whether the clipboard still holds it, and the random token that
+/// is recorded as the last copy.
+#[derive(Debug, Clone)]
+pub(crate) struct Copied {
+    token: String,
+    digest: [u8; 32],
+}
+
+fn digest(value: &str) -> [u8; 32] {
+    Sha256::digest(value.as_bytes()).into()
+}
+
+/// # Last Copy
+/// The file that holds the token of the last copy from the vault, so that a
+/// clearing that is still due leaves a later copy of the same value alone.
+pub(crate) struct LastCopy {
+    path: PathBuf,
+}
+
+impl LastCopy {
+    /// the file in the runtime directory of the user if there is one
+    pub(crate) fn new() -> Self {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        #[cfg(unix)]
+        // SAFETY: `getuid` always succeeds
+        let name = format!("vault-clipboard-{}", unsafe { libc::getuid() });
+        #[cfg(not(unix))]
+        let name = "vault-clipboard".to_owned();
+        Self::at(dir.join(name))
+    }
+
+    pub(crate) fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// whether nothing else has been copied from the vault since
+    fn is_last(&self, copied: &Copied) -> bool {
+        std::fs::read_to_string(&self.path).is_ok_and(|token| token == copied.token)
+    }
+
+    /// forgets the copy, so that no clearing that is still due clears
+    /// whatever is copied next
+    fn forget(&self, copied: &Copied) {
+        if self.is_last(copied) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    /// # Copy
+    /// Copies the value and records it as the last copy.
+    pub(crate) fn copy(
+        &self,
+        clipboard: &mut dyn Clipboard,
+        value: &str,
+    ) -> Result<Copied, String> {
+        clipboard.copy(value)?;
+        let copied = Copied {
+            token: hex::encode(rand::random::<[u8; 16]>()),
+            digest: digest(value),
+        };
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            // the directory may be shared with other users
+            options.mode(0o600).custom_flags(libc::O_NOFOLLOW);
+        }
+        options
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(copied.token.as_bytes()))
+            .map_err(|e| format!("Could not record the copy in {}: {e}", self.path.display()))?;
+        Ok(copied)
+    }
+
+    /// # Clear If Unchanged
+    /// Clears the clipboard, but only if it still holds the value and nothing
+    /// else has been copied from the vault since, so that whatever was copied
+    /// after it is not lost. A clipboard that can not be read is left alone.
+    pub(crate) fn clear_if_unchanged(
+        &self,
+        clipboard: &mut dyn Clipboard,
+        copied: &Copied,
+    ) -> Result<ClearOutcome, String> {
+        if !self.is_last(copied) {
+            return Ok(ClearOutcome::Changed);
+        }
+        let outcome = match clipboard.contents()? {
+            Some(contents) if digest(&contents) == copied.digest => {
+                clipboard.clear()?;
+                ClearOutcome::Cleared
+            }
+            Some(_) => ClearOutcome::Changed,
+            None => return Ok(ClearOutcome::Unreadable),
+        };
+        self.forget(copied);
+        Ok(outcome)
+    }
+
+    /// # Clear Later
+    /// Clears the clipboard after the given time from a process of its own,
+    /// so that nobody has to wait for it. That process runs the hidden
+    /// `clear-clipboard` command and gets the digest through a pipe, which
+    /// keeps it out of the arguments that every user can list.
+    pub(crate) fn clear_later(&self, copied: &Copied, duration: Duration) -> Result<(), String> {
+        let exe = std::env::current_exe().map_err(|e| format!("Could not find vault: {e}"))?;
+        // the process stays in the process group of vault, as only the
+        // foreground may read the clipboard from the terminal
+        let mut child = Command::new(exe)
+            .arg("clear-clipboard")
+            .arg(&copied.token)
+            .arg("--after")
+            .arg(duration.as_secs().to_string())
+            .arg("--last-copy")
+            .arg(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Could not clear the clipboard later: {e}"))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "{}", hex::encode(copied.digest))
+                .map_err(|e| format!("Could not clear the clipboard later: {e}"))?;
+        }
+        // the interactive shell lives on, so the process is waited for
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+/// # Clear After
+/// Reads the digest of the copy of the token from stdin, waits for the
+/// given time and clears the clipboard if it still holds the copied value.
+pub(crate) fn clear_after(
+    last_copy: &LastCopy,
+    token: String,
+    duration: Duration,
+) -> Result<ClearOutcome, String> {
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| format!("Could not read the digest: {e}"))?;
+    let digest = hex::decode(line.trim())
+        .ok()
+        .and_then(|digest| digest.try_into().ok())
+        .ok_or("The digest is not valid")?;
+    std::thread::sleep(duration);
+    last_copy.clear_if_unchanged(&mut Osc52::new()?, &Copied { token, digest })
+}
+
+/// # OSC 52
+/// Sets the clipboard with the OSC 52 escape sequence of the terminal, which
+/// also works over SSH. The terminal is asked for the clipboard with the same
+/// sequence, which only terminals that allow it answer.
+pub(crate) struct Osc52 {
+    tty: File,
+}
+
+impl Osc52 {
+    /// opens the controlling terminal, so that nothing is written into a pipe
+    pub(crate) fn new() -> Result<Self, String> {
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .map_err(|e| format!("There is no terminal to copy to: {e}"))?;
+        Ok(Self { tty })
+    }
+
+    fn send(&mut self, sequence: &str) -> Result<(), String> {
+        self.tty
+            .write_all(sequence.as_bytes())
+            .and_then(|_| self.tty.flush())
+            .map_err(|e| format!("Could not write to the terminal: {e}"))
+    }
+}
+
+/// returns the escape sequence that puts the value into the clipboard
+fn osc52(value: &str) -> String {
+    format!("\x1b]52;c;{}\x07", BASE64_STANDARD.encode(value))
+}
+
+/// returns the contents of the clipboard from the answer of the terminal,
+/// which ends with either BEL or ST
+fn parse_reply(reply: &[u8]) -> Option<String> {
+    let reply = String::from_utf8_lossy(reply);
+    let (_, data) = reply.split_once("\x1b]52;")?;
+    let (_, data) = data.split_once(';')?;
+    let data = data
+        .strip_suffix('\x07')
+        .or_else(|| data.strip_suffix("\x1b\\"))?;
+    let decoded = BASE64_STANDARD.decode(data).ok()?;
+    String::from_utf8(decoded).ok()
+}
+
+impl Clipboard for Osc52 {
+    fn copy(&mut self, value: &str) -> Result<(), String> {
+        self.send(&osc52(value))
+    }
+
+    fn contents(&mut self) -> Result<Option<String>, String> {
+        Ok(query(&mut self.tty, QUERY_TIMEOUT).and_then(|reply| parse_reply(&reply)))
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.send(&osc52(""))
+    }
+}
+
+/// asks the terminal for the clipboard. The terminal is switched to raw mode
+/// meanwhile, so that the answer is neither echoed nor waits for a newline
+#[cfg(unix)]
+fn query(tty: &mut File, timeout: Duration) -> Option<Vec<u8>> {
+    use std::io::Read;
+    use std::os::fd::AsRawFd;
+    use std::time::Instant;
+
+    let fd = tty.as_raw_fd();
+    // only the foreground may read the terminal, anyone else would be stopped
+    // and its answer would be typed into whatever runs in the foreground
+    // SAFETY: `fd` stays open while `tty` lives
+    if unsafe { libc::tcgetpgrp(fd) != libc::getpgrp() } {
+        return None;
+    }
+    // SAFETY: `fd` stays open while `tty` lives, and `tcgetattr` initializes
+    // the attributes when it succeeds
+    let original = unsafe {
+        let mut original = std::mem::MaybeUninit::<libc::termios>::uninit();
+        if libc::tcgetattr(fd, original.as_mut_ptr()) != 0 {
+            return None;
+        }
+        original.assume_init()
+    };
+    let mut raw = original;
+    // SAFETY: `raw` is a valid copy of the attributes of the terminal
+    unsafe {
+        libc::cfmakeraw(&mut raw);
+        if libc::tcsetattr(fd, libc::TCSANOW, &raw) != 0 {
+            return None;
+        }
+    }
+
+    let mut read_reply = || {
+        tty.write_all(b"\x1b]52;c;?\x07").ok()?;
+        tty.flush().ok()?;
+        let deadline = Instant::now() + timeout;
+        let mut reply = Vec::new();
+        while !(reply.ends_with(b"\x07") || reply.ends_with(b"\x1b\\")) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let mut poll = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: `poll` is a single valid pollfd
+            if left.is_zero() || unsafe { libc::poll(&mut poll, 1, left.as_millis() as i32) } <= 0 {
+                return None;
+            }
+            let mut buffer = [0; 1024];
+            match tty.read(&mut buffer).ok()? {
+                0 => return None,
+                len => reply.extend_from_slice(&buffer[..len]),
+            }
+        }
+        Some(reply)
+    };
+    let reply = read_reply();
+    drain(tty, fd);
+
+    // SAFETY: `original` are the attributes the terminal had before
+    unsafe {
+        libc::tcsetattr(fd, libc::TCSANOW, &original);
+    }
+    reply
+}
+
+/// reads and drops whatever the terminal still sends, until it is quiet
+#[cfg(unix)]
+fn drain(tty: &mut File, fd: std::os::fd::RawFd) {
+    use std::io::Read;
+
+    loop {
+        let mut poll = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `poll` is a single valid pollfd
+        if unsafe { libc::poll(&mut poll, 1, DRAIN_TIMEOUT.as_millis() as i32) } <= 0 {
+            return;
+        }
+        if matches!(tty.read(&mut [0; 1024]), Ok(0) | Err(_)) {
+            return;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn query(_tty: &mut File, _timeout: Duration) -> Option<Vec<u8>> {
+    None
+}
+
+/// a clipboard in memory for the tests, `contents` is `None` while it is not
+/// readable
+#[cfg(test)]
+pub(crate) struct MemoryClipboard {
+    pub(crate) value: String,
+    pub(crate) readable: bool,
+}
+
+#[cfg(test)]
+impl Clipboard for MemoryClipboard {
+    fn copy(&mut self, value: &str) -> Result<(), String> {
+        self.value = value.to_owned();
+        Ok(())
+    }
+
+    fn contents(&mut self) -> Result<Option<String>, String> {
+        Ok(self.readable.then(|| self.value.clone()))
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.value.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn test_clear_if_unchanged() {
+        let dir = TempDir::new("clipboard");
+        let last_copy = LastCopy::at(dir.join("last-copy"));
+        let mut clipboard = MemoryClipboard {
+            value: String::new(),
+            readable: true,
+        };
+        let copied = last_copy.copy(&mut clipboard, "secret").unwrap();
+        assert_eq!(
+            last_copy.clear_if_unchanged(&mut clipboard, &copied),
+            Ok(ClearOutcome::Cleared)
+        );
+        assert_eq!(clipboard.value, "");
+
+        let copied = last_copy.copy(&mut clipboard, "secret").unwrap();
+        clipboard.copy("copied elsewhere").unwrap();
+        assert_eq!(
+            last_copy.clear_if_unchanged(&mut clipboard, &copied),
+            Ok(ClearOutcome::Changed)
+        );
+        assert_eq!(clipboard.value, "copied elsewhere");
+
+        // a clipboard that can not be read is left alone
+        let copied = last_copy.copy(&mut clipboard, "secret").unwrap();
+        clipboard.copy("copied elsewhere").unwrap();
+        clipboard.readable = false;
+        assert_eq!(
+            last_copy.clear_if_unchanged(&mut clipboard, &copied),
+            Ok(ClearOutcome::Unreadable)
+        );
+        assert_eq!(clipboard.value, "copied elsewhere");
+
+        // a later copy of the same value is only cleared for itself
+        clipboard.readable = true;
+        let first = last_copy.copy(&mut clipboard, "secret").unwrap();
+        let second = last_copy.copy(&mut clipboard, "secret").unwrap();
+        assert_eq!(
+            last_copy.clear_if_unchanged(&mut clipboard, &first),
+            Ok(ClearOutcome::Changed)
+        );
+        assert_eq!(clipboard.value, "secret");
+        assert_eq!(
+            last_copy.clear_if_unchanged(&mut clipboard, &second),
+            Ok(ClearOutcome::Cleared)
+        );
+        assert_eq!(clipboard.value, "");
+    }
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(osc52("hunter2"), "\x1b]52;c;aHVudGVyMg==\x07");
+        assert_eq!(osc52(""), "\x1b]52;c;\x07");
+        assert_eq!(
+            parse_reply(b"\x1b]52;c;aHVudGVyMg==\x07").as_deref(),
+            Some("hunter2")
+        );
+        assert_eq!(
+            parse_reply(b"\x1b]52;c;aHVudGVyMg==\x1b\\").as_deref(),
+            Some("hunter2")
+        );
+        assert_eq!(parse_reply(b"\x1b]52;c;\x07").as_deref(), Some(""));
+        assert_eq!(parse_reply(b"\x1b[?1;2c"), None);
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::totp::Totp;
+
+/// names of the fields every entry has, anything else is a custom field
+pub(crate) const FIELDS: [&str; 6] = ["title", "username", "password", "url", "notes", "otp"];
+
+/// fields that are not shown unless they are asked for
+pub(crate) const SECRET_FIELDS: [&str; 2] = ["password", "otp"];
+
+/// names that can not be used for custom fields, as they are not plain text
+const RESERVED: [&str; 3] = ["tags", "created", "modified"];
+
+/// # Entry
+/// A record of the vault. Every entry is serialized and encrypted on its
+/// own, under the key it is stored with.
+///
+/// - `title`: human readable name, the key by default
+/// - `username`, `password`, `url` and `notes`: the usual credentials
+/// - `otp`: an `otpauth://` URI or a base32 secret of a TOTP generator
+/// - `tags`: labels to filter entries by
+/// - `fields`: any other named values, e.g. recovery codes
+/// - `created` and `modified`: unix timestamps in seconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Entry {
+    pub(crate) title: String,
+    pub(crate) username: String,
+    pub(crate) password: String,
+    pub(crate) url: String,
+    pub(crate) notes: String,
+    pub(crate) otp: String,
+    pub(crate) tags: BTreeSet<String>,
+    pub(crate) fields: BTreeMap<String, String>,
+    pub(crate) created: i64,
+    pub(crate) modified: i64,
+}
+
+/// returns the current unix timestamp in seconds
+pub(crate) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// formats a unix timestamp for the terminal
+pub(crate) fn format_time(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_default()
+}
+
+impl Entry {
+    /// creates an empty entry with the given title, created just now
+    pub(crate) fn new(title: impl Into<String>) -> Self {
+        let now = now();
+        Self {
+            title: title.into(),
+            created: now,
+            modified: now,
+            ..Self::default()
+        }
+    }
+
+    /// returns the TOTP generator of the entry, if it has one
+    pub(crate) fn totp(&self) -> Result<Option<Totp>, String> {
+        if self.otp.is_empty() {
+            return Ok(None);
+        }
+        Totp::parse(&self.otp).map(Some)
+    }
+
+    pub(crate) fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// returns the value of a field by its name, either one of `FIELDS` or a
+    /// custom field
+    pub(crate) fn field(&self, name: &str) -> Option<&str> {
+        match name {
+            "title" => Some(&self.title),
+            "username" => Some(&self.username),
+            "password" => Some(&self.password),
+            "url" => Some(&self.url),
+            "notes" => Some(&self.notes),
+            "otp" => Some(&self.otp),
+            _ => self.fields.get(name).map(String::as_str),
+        }
+    }
+
+    /// sets a field by its name, names that are not in `FIELDS` are stored as
+    /// custom fields
+    pub(crate) fn set_field(&mut self, name: &str, value: String) -> Result<(), String> {
+        match name {
+            "title" => self.title = value,
+            "username" => self.username = value,
+            "password" => self.password = value,
+            "url" => self.url = value,
+            "notes" => self.notes = value,
+            "otp" => {
+                // only secrets that codes can be generated from are stored
+                Totp::parse(&value)?;
+                self.otp = value;
+            }
+            _ if name.is_empty() || RESERVED.contains(&name) => {
+                return Err(format!("'{name}' can not be used as a field name"));
+            }
+            _ => {
+                self.fields.insert(name.to_owned(), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// clears a field, custom fields are removed. Returns whether the field
+    /// had a value
+    pub(crate) fn unset_field(&mut self, name: &str) -> bool {
+        match name {
+            "title" => !std::mem::take(&mut self.title).is_empty(),
+            "username" => !std::mem::take(&mut self.username).is_empty(),
+            "password" => !std::mem::take(&mut self.password).is_empty(),
+            "url" => !std::mem::take(&mut self.url).is_empty(),
+            "notes" => !std::mem::take(&mut self.notes).is_empty(),
+            "otp" => !std::mem::take(&mut self.otp).is_empty(),
+            _ => self.fields.remove(name).is_some(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        let mut entry = Entry::new("Database");
+        assert_eq!(entry.created, entry.modified);
+        entry.set_field("password", "a b c".to_owned()).unwrap();
+        entry.set_field("pin", "1234".to_owned()).unwrap();
+        assert!(entry.set_field("tags", "x".to_owned()).is_err());
+        assert!(entry.set_field("otp", "not base32!".to_owned()).is_err());
+        assert_eq!(entry.totp(), Ok(None));
+        entry.set_field("otp", "GEZDGNBV".to_owned()).unwrap();
+        assert!(entry.totp().unwrap().is_some());
+        assert_eq!(entry.field("password"), Some("a b c"));
+        assert_eq!(entry.field("pin"), Some("1234"));
+        assert_eq!(entry.field("title"), Some("Database"));
+        assert_eq!(entry.field("missing"), None);
+
+        assert!(entry.unset_field("pin"));
+        assert!(!entry.unset_field("pin"));
+        assert!(entry.unset_field("password"));
+        assert_eq!(entry.field("password"), Some(""));
+
+        entry.tags.insert("work".to_owned());
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(serde_json::from_str::<Entry>(&json).unwrap(), entry);
+        assert!(entry.has_tag("work"));
+        // fields that are added later default to empty
+        assert_eq!(
+            serde_json::from_str::<Entry>(r#"{"title":"a"}"#).unwrap(),
+            Entry {
+                title: "a".to_owned(),
+                ..Entry::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00 UTC");
+    }
+}
@@ -0,0 +1,688 @@
+//! # Exchange
+//! Imports entries from other password managers and exports them in their
+//! formats, so that credentials can be moved in both directions:
+//!
+//! - KeePass CSV as exported by KeePassXC (`Group`, `Title`, `Username`, ...)
+//!   and KeePass 2 (`Account`, `Login Name`, ...)
+//! - Bitwarden JSON, unencrypted
+//! - 1Password CSV (`Title`, `Url`, `Username`, `Password`, `OTPAuth`, ...)
+//!
+//! CSV columns that are not known become custom fields, and custom fields are
+//! exported as additional columns.
+//!
+//! The key of an entry is exported in the custom field `vault key` when it is
+//! not the title of the entry. The tags are exported in a `Tags` column, and
+//! Bitwarden, which has folders instead, gets the first tag as the folder and
+//! all tags in the custom field `tags`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, SecondsFormat};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::entry::{Entry, now};
+use crate::totp::Totp;
+
+/// custom field with the key of an entry whose title is not its key
+const KEY_FIELD: &str = "vault key";
+/// custom field with the tags of a Bitwarden item
+const TAGS_FIELD: &str = "tags";
+
+/// # Format
+/// The files entries can be imported from and exported to. A `backup` is a
+/// copy of the vault file, the other formats are plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Format {
+    Backup,
+    Keepass,
+    Bitwarden,
+    #[value(name = "1password")]
+    OnePassword,
+}
+
+/// # Conflict
+/// What happens to an imported entry whose key already exists.
+///
+/// - `Skip`: the existing entry is kept
+/// - `Overwrite`: the existing entry is replaced
+/// - `Rename`: the imported entry is stored as `key (2)`, `key (3)`, ...
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Conflict {
+    #[default]
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+/// how many of the imported entries were added, and how conflicts were
+/// resolved
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ImportReport {
+    pub(crate) added: usize,
+    pub(crate) overwritten: usize,
+    pub(crate) renamed: usize,
+    pub(crate) skipped: usize,
+}
+
+/// where the value of a CSV column goes
+#[derive(Debug, Clone, PartialEq)]
+enum Column {
+    /// one of the fields every entry has
+    Field(&'static str),
+    /// a KeePass group path, its last group becomes a tag
+    Group,
+    /// tags separated by commas or semicolons
+    Tags,
+    Created,
+    Modified,
+    /// columns that have no meaning in the vault
+    Ignored,
+    Custom(String),
+}
+
+impl Column {
+    /// maps the header of a column of any of the CSV formats
+    fn from_header(header: &str) -> Self {
+        match header.trim().to_lowercase().as_str() {
+            "title" | "account" | "name" => Self::Field("title"),
+            "username" | "user name" | "login name" | "login" => Self::Field("username"),
+            "password" => Self::Field("password"),
+            "url" | "website" | "web site" | "login uri" => Self::Field("url"),
+            "notes" | "note" | "comments" => Self::Field("notes"),
+            "totp" | "otpauth" | "otp" | "one-time password" => Self::Field("otp"),
+            "group" | "folder" => Self::Group,
+            "tags" => Self::Tags,
+            "created" | "creation time" => Self::Created,
+            "last modified" | "modified" | "last modification time" => Self::Modified,
+            "icon" | "favorite" | "archived" | "type" => Self::Ignored,
+            _ => Self::Custom(header.trim().to_owned()),
+        }
+    }
+}
+
+/// returns the key an entry is stored under when no key was exported
+fn title_key(entry: &Entry) -> String {
+    match entry.title.trim() {
+        "" => "untitled".to_owned(),
+        title => title.to_owned(),
+    }
+}
+
+/// # Parse
+/// Reads the entries of an exported file along with the keys they are
+/// stored under, which are the exported keys or else their titles. Entries
+/// with the same key are stored as `key (2)`, `key (3)`, ...
+pub(crate) fn parse(format: Format, content: &str) -> Result<Vec<(String, Entry)>, String> {
+    let entries = match format {
+        Format::Keepass | Format::OnePassword => parse_csv_entries(content)?,
+        Format::Bitwarden => parse_bitwarden(content)?,
+        Format::Backup => return Err("A backup is not a text file".to_owned()),
+    };
+    let mut keys = BTreeSet::new();
+    Ok(entries
+        .into_iter()
+        .map(|mut entry| {
+            let key = match entry.fields.remove(KEY_FIELD) {
+                Some(key) if !key.trim().is_empty() => key,
+                _ => title_key(&entry),
+            };
+            let key = match keys.contains(&key) {
+                true => (2..)
+                    .map(|n| format!("{key} ({n})"))
+                    .find(|key| !keys.contains(key))
+                    .unwrap(),
+                false => key,
+            };
+            keys.insert(key.clone());
+            if entry.created == 0 {
+                entry.created = now();
+            }
+            if entry.modified == 0 {
+                entry.modified = entry.created;
+            }
+            (key, entry)
+        })
+        .collect())
+}
+
+/// # Write
+/// Writes the entries in the given plain text format.
+pub(crate) fn write(format: Format, entries: &[(String, Entry)]) -> Result<String, String> {
+    // the key is only exported when the title does not give it back
+    let entries: Vec<(String, Entry)> = entries
+        .iter()
+        .map(|(key, entry)| {
+            let mut entry = entry.clone();
+            if *key != title_key(&entry) {
+                entry.fields.insert(KEY_FIELD.to_owned(), key.clone());
+            }
+            (key.clone(), entry)
+        })
+        .collect();
+    match format {
+        Format::Keepass => Ok(write_keepass(&entries)),
+        Format::OnePassword => Ok(write_one_password(&entries)),
+        Format::Bitwarden => write_bitwarden(&entries),
+        Format::Backup => Err("A backup is not a text file".to_owned()),
+    }
+}
+
+/// joins the tags of an entry for the `Tags` column
+fn join_tags(entry: &Entry) -> String {
+    let tags: Vec<&str> = entry.tags.iter().map(String::as_str).collect();
+    tags.join(",")
+}
+
+/// splits the tags of a `Tags` column
+fn split_tags(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_owned)
+}
+
+/// parses a timestamp of an export, which are RFC 3339 in every format
+fn parse_time(value: &str) -> i64 {
+    DateTime::parse_from_rfc3339(value.trim()).map_or(0, |time| time.timestamp())
+}
+
+fn format_time(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+/// stores an imported TOTP secret, or keeps it as a custom field if no codes
+/// can be generated from it
+fn set_otp(entry: &mut Entry, value: String) {
+    if Totp::parse(&value).is_ok() {
+        entry.otp = value;
+    } else {
+        entry.fields.insert("totp".to_owned(), value);
+    }
+}
+
+fn parse_csv_entries(content: &str) -> Result<Vec<Entry>, String> {
+    let mut rows = parse_csv(content)?.into_iter();
+    let columns: Vec<Column> = rows
+        .next()
+        .ok_or_else(|| "The file is empty".to_owned())?
+        .iter()
+        .map(|header| Column::from_header(header))
+        .collect();
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let mut entry = Entry::default();
+        for (column, value) in columns.iter().zip(row) {
+            if value.is_empty() {
+                continue;
+            }
+            match column {
+                Column::Field("otp") => set_otp(&mut entry, value),
+                Column::Field(name) => entry.set_field(name, value)?,
+                Column::Group => {
+                    // KeePassXC puts every group below `Root`
+                    if let Some(group) = value.rsplit('/').next().filter(|group| *group != "Root") {
+                        entry.tags.insert(group.to_owned());
+                    }
+                }
+                Column::Tags => entry.tags.extend(split_tags(&value)),
+                Column::Created => entry.created = parse_time(&value),
+                Column::Modified => entry.modified = parse_time(&value),
+                Column::Ignored => {}
+                Column::Custom(name) => {
+                    entry.fields.insert(name.clone(), value);
+                }
+            }
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// returns the names of the custom fields of all entries, which are exported
+/// as additional columns
+fn custom_columns(entries: &[(String, Entry)]) -> Vec<&str> {
+    let names: BTreeSet<&str> = entries
+        .iter()
+        .flat_map(|(_, entry)| entry.fields.keys().map(String::as_str))
+        .collect();
+    names.into_iter().collect()
+}
+
+/// writes the rows of a CSV file, each with the custom fields of its entry
+fn write_csv_entries<const N: usize>(
+    headers: [&str; N],
+    entries: &[(String, Entry)],
+    row: impl Fn(&Entry) -> [String; N],
+) -> String {
+    let custom = custom_columns(entries);
+    let mut csv = String::new();
+    write_csv_row(&mut csv, headers.iter().chain(&custom).copied());
+    for (_, entry) in entries {
+        let fields = custom
+            .iter()
+            .map(|name| entry.fields.get(*name).map_or("", String::as_str));
+        write_csv_row(
+            &mut csv,
+            row(entry).iter().map(String::as_str).chain(fields),
+        );
+    }
+    csv
+}
+
+fn write_keepass(entries: &[(String, Entry)]) -> String {
+    let headers = [
+        "Group",
+        "Title",
+        "Username",
+        "Password",
+        "URL",
+        "Notes",
+        "TOTP",
+        "Tags",
+        "Last Modified",
+        "Created",
+    ];
+    write_csv_entries(headers, entries, |entry| {
+        // a group holds any number of entries, but an entry is only in one,
+        // so the other tags are only in the `Tags` column
+        let group = match entry.tags.first() {
+            Some(tag) => format!("Root/{tag}"),
+            None => "Root".to_owned(),
+        };
+        [
+            group,
+            entry.title.clone(),
+            entry.username.clone(),
+            entry.password.clone(),
+            entry.url.clone(),
+            entry.notes.clone(),
+            entry.otp.clone(),
+            join_tags(entry),
+            format_time(entry.modified),
+            format_time(entry.created),
+        ]
+    })
+}
+
+fn write_one_password(entries: &[(String, Entry)]) -> String {
+    let headers = [
+        "Title", "Url", "Username", "Password", "OTPAuth", "Tags", "Notes",
+    ];
+    write_csv_entries(headers, entries, |entry| {
+        [
+            entry.title.clone(),
+            entry.url.clone(),
+            entry.username.clone(),
+            entry.password.clone(),
+            entry.otp.clone(),
+            join_tags(entry),
+            entry.notes.clone(),
+        ]
+    })
+}
+
+/// # CSV
+/// Parses CSV of RFC 4180: fields are separated by commas, and fields in
+/// double quotes can contain commas, line breaks and doubled quotes.
+fn parse_csv(content: &str) -> Result<Vec<Vec<String>>, String> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut chars = content.chars().peekable();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("The CSV file ends inside a quoted field".to_owned());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    // blank lines are not rows
+    rows.retain(|row| row.len() > 1 || row.first().is_some_and(|field| !field.is_empty()));
+    Ok(rows)
+}
+
+/// writes a row of CSV, every field is quoted
+fn write_csv_row<'a>(csv: &mut String, fields: impl IntoIterator<Item = &'a str>) {
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|field| format!("\"{}\"", field.replace('"', "\"\"")))
+        .collect();
+    csv.push_str(&fields.join(","));
+    csv.push('\n');
+}
+
+/// # Bitwarden
+/// The parts of an unencrypted Bitwarden export the vault knows about.
+/// Bitwarden writes `null` for missing values, so everything is optional.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct BitwardenExport {
+    encrypted: bool,
+    folders: Option<Vec<BitwardenFolder>>,
+    items: Option<Vec<BitwardenItem>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct BitwardenFolder {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct BitwardenItem {
+    id: Option<String>,
+    folder_id: Option<String>,
+    /// 1 for logins, 2 for secure notes, 3 for cards and 4 for identities
+    #[serde(rename = "type")]
+    kind: u8,
+    name: Option<String>,
+    notes: Option<String>,
+    login: Option<BitwardenLogin>,
+    fields: Option<Vec<BitwardenField>>,
+    creation_date: Option<String>,
+    revision_date: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct BitwardenLogin {
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+    uris: Option<Vec<BitwardenUri>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct BitwardenUri {
+    uri: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct BitwardenField {
+    name: Option<String>,
+    value: Option<String>,
+    /// 0 for text and 1 for hidden fields
+    #[serde(rename = "type")]
+    kind: u8,
+}
+
+fn parse_bitwarden(content: &str) -> Result<Vec<Entry>, String> {
+    let export: BitwardenExport =
+        serde_json::from_str(content).map_err(|e| format!("Invalid Bitwarden export: {e}"))?;
+    if export.encrypted {
+        return Err("Encrypted Bitwarden exports can not be imported".to_owned());
+    }
+    let folders: BTreeMap<String, String> = export
+        .folders
+        .unwrap_or_default()
+        .into_iter()
+        .map(|folder| (folder.id, folder.name))
+        .collect();
+
+    let mut entries = Vec::new();
+    for item in export.items.unwrap_or_default() {
+        let mut entry = Entry {
+            title: item.name.unwrap_or_default(),
+            notes: item.notes.unwrap_or_default(),
+            created: item.creation_date.as_deref().map_or(0, parse_time),
+            modified: item.revision_date.as_deref().map_or(0, parse_time),
+            ..Entry::default()
+        };
+        if let Some(folder) = item.folder_id.and_then(|id| folders.get(&id)) {
+            entry.tags.insert(folder.clone());
+        }
+        if let Some(login) = item.login {
+            entry.username = login.username.unwrap_or_default();
+            entry.password = login.password.unwrap_or_default();
+            if let Some(totp) = login.totp.filter(|totp| !totp.is_empty()) {
+                set_otp(&mut entry, totp);
+            }
+            let uris = login.uris.unwrap_or_default().into_iter();
+            for (i, uri) in uris.filter_map(|uri| uri.uri).enumerate() {
+                match i {
+                    0 => entry.url = uri,
+                    i => {
+                        entry.fields.insert(format!("url {}", i + 1), uri);
+                    }
+                }
+            }
+        }
+        for field in item.fields.unwrap_or_default() {
+            let value = field.value.unwrap_or_default();
+            match field.name.filter(|name| !name.is_empty()) {
+                Some(name) if name == TAGS_FIELD => entry.tags.extend(split_tags(&value)),
+                Some(name) => {
+                    entry.fields.insert(name, value);
+                }
+                None => {}
+            }
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// returns a random id in the format of a UUID, as Bitwarden uses them
+fn random_id() -> String {
+    let bytes = hex::encode(crate::vault::Crypto::generate_salt(16));
+    format!(
+        "{}-{}-{}-{}-{}",
+        &bytes[..8],
+        &bytes[8..12],
+        &bytes[12..16],
+        &bytes[16..20],
+        &bytes[20..]
+    )
+}
+
+fn write_bitwarden(entries: &[(String, Entry)]) -> Result<String, String> {
+    // like a KeePass group, the first tag becomes the folder, and all tags
+    // are kept in a custom field
+    let folders: BTreeMap<&str, String> = entries
+        .iter()
+        .filter_map(|(_, entry)| entry.tags.first())
+        .map(|tag| (tag.as_str(), random_id()))
+        .collect();
+
+    let items = entries
+        .iter()
+        .map(|(_, entry)| {
+            let optional = |value: &str| (!value.is_empty()).then(|| value.to_owned());
+            BitwardenItem {
+                id: Some(random_id()),
+                folder_id: entry.tags.first().map(|tag| folders[tag.as_str()].clone()),
+                kind: 1,
+                name: Some(entry.title.clone()),
+                notes: optional(&entry.notes),
+                login: Some(BitwardenLogin {
+                    username: optional(&entry.username),
+                    password: optional(&entry.password),
+                    totp: optional(&entry.otp),
+                    uris: Some(
+                        optional(&entry.url)
+                            .map(|uri| BitwardenUri { uri: Some(uri) })
+                            .into_iter()
+                            .collect(),
+                    ),
+                }),
+                fields: Some(
+                    entry
+                        .fields
+                        .iter()
+                        .map(|(name, value)| (name.clone(), value.clone()))
+                        .chain(
+                            (entry.tags.len() > 1)
+                                .then(|| (TAGS_FIELD.to_owned(), join_tags(entry))),
+                        )
+                        .map(|(name, value)| BitwardenField {
+                            name: Some(name),
+                            value: Some(value),
+                            kind: 0,
+                        })
+                        .collect(),
+                ),
+                creation_date: Some(format_time(entry.created)),
+                revision_date: Some(format_time(entry.modified)),
+            }
+        })
+        .collect();
+
+    let export = BitwardenExport {
+        encrypted: false,
+        folders: Some(
+            folders
+                .into_iter()
+                .map(|(name, id)| BitwardenFolder {
+                    id,
+                    name: name.to_owned(),
+                })
+                .collect(),
+        ),
+        items: Some(items),
+    };
+    serde_json::to_string_pretty(&export).map_err(|e| format!("Could not write the export: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<(String, Entry)> {
+        let entry = Entry {
+            title: "GitHub".to_owned(),
+            username: "ci@example.com".to_owned(),
+            password: "p\"a,s\ns".to_owned(),
+            url: "https://github.com".to_owned(),
+            notes: "shared, \"bot\" account".to_owned(),
+            otp: "GEZDGNBVGY3TQOJQ".to_owned(),
+            tags: BTreeSet::from(["work".to_owned(), "ci".to_owned()]),
+            fields: BTreeMap::from([("recovery".to_owned(), "1234 5678".to_owned())]),
+            created: 1_700_000_000,
+            modified: 1_700_000_100,
+        };
+        let note = Entry {
+            title: "Wifi".to_owned(),
+            password: "hunter2".to_owned(),
+            created: 1_700_000_000,
+            modified: 1_700_000_000,
+            ..Entry::default()
+        };
+        // the key of the note is not its title
+        vec![("GitHub".to_owned(), entry), ("home wifi".to_owned(), note)]
+    }
+
+    #[test]
+    fn test_csv() {
+        let rows = parse_csv("a,\"b,c\",\"d\"\"e\"\r\n\n\"f\ng\",,\n").unwrap();
+        assert_eq!(rows, [vec!["a", "b,c", "d\"e"], vec!["f\ng", "", ""]]);
+        assert!(parse_csv("\"open").is_err());
+
+        let mut csv = String::new();
+        write_csv_row(&mut csv, ["a", "b,c", "d\"e"]);
+        assert_eq!(parse_csv(&csv).unwrap(), [vec!["a", "b,c", "d\"e"]]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for format in [Format::Keepass, Format::Bitwarden] {
+            let exported = write(format, &sample()).unwrap();
+            assert_eq!(parse(format, &exported).unwrap(), sample(), "{format:?}");
+        }
+        // 1Password has no timestamps
+        let exported = write(Format::OnePassword, &sample()).unwrap();
+        let imported = parse(Format::OnePassword, &exported).unwrap();
+        for ((key, mut entry), (expected_key, expected)) in imported.into_iter().zip(sample()) {
+            (entry.created, entry.modified) = (expected.created, expected.modified);
+            assert_eq!((key, entry), (expected_key, expected));
+        }
+    }
+
+    #[test]
+    fn test_keepass() {
+        let csv = "\"Account\",\"Login Name\",\"Password\",\"Web Site\",\"Comments\"\n\
+                   \"Mail\",\"me\",\"secret\",\"https://mail.example.com\",\"\"\n\
+                   \"\",\"root\",\"toor\",\"\",\"no title\"\n";
+        let entries = parse(Format::Keepass, csv).unwrap();
+        assert_eq!(entries[0].0, "Mail");
+        assert_eq!(entries[0].1.url, "https://mail.example.com");
+        assert_eq!(entries[1].0, "untitled");
+        assert_eq!(entries[1].1.notes, "no title");
+
+        // entries with the same title are not lost
+        let csv = "Title,Password\nMail,a\nMail,b\n";
+        let keys: Vec<String> = parse(Format::Keepass, csv)
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, ["Mail", "Mail (2)"]);
+
+        let csv = "Group,Title,Password,TOTP,Icon\nRoot/Servers,db,x,invalid!,0\n";
+        let (_, entry) = parse(Format::Keepass, csv).unwrap().remove(0);
+        assert!(entry.has_tag("Servers"));
+        assert_eq!(entry.fields["totp"], "invalid!");
+        assert!(entry.created > 0);
+    }
+
+    #[test]
+    fn test_bitwarden() {
+        let json = r#"{
+            "encrypted": false,
+            "folders": [{"id": "f1", "name": "Work"}],
+            "items": [{
+                "id": "i1", "folderId": "f1", "type": 1, "name": "Mail", "notes": null,
+                "login": {"username": "me", "password": "pw", "totp": null,
+                          "uris": [{"match": null, "uri": "https://a"}, {"uri": "https://b"}]},
+                "fields": [{"name": "pin", "value": "1234", "type": 1}],
+                "creationDate": "2023-11-14T22:13:20.000Z",
+                "revisionDate": "2023-11-14T22:15:00.000Z"
+            }, {"type": 2, "name": "Note", "notes": "text", "fields": null}]
+        }"#;
+        let entries = parse(Format::Bitwarden, json).unwrap();
+        let (key, entry) = &entries[0];
+        assert_eq!(key, "Mail");
+        assert_eq!(
+            (entry.url.as_str(), entry.fields["url 2"].as_str()),
+            ("https://a", "https://b")
+        );
+        assert_eq!(entry.fields["pin"], "1234");
+        assert!(entry.has_tag("Work"));
+        assert_eq!(
+            (entry.created, entry.modified),
+            (1_700_000_000, 1_700_000_100)
+        );
+        assert_eq!(entries[1].1.notes, "text");
+
+        assert!(parse(Format::Bitwarden, r#"{"encrypted": true}"#).is_err());
+    }
+}
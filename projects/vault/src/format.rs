@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use argon2::Params;
+
+/// first bytes of every vault file since version 1
+pub(crate) const MAGIC: &[u8; 4] = b"RVLT";
+/// version of the file format that is written, version 1 stored plain
+/// passwords and version 2 stores entries
+pub(crate) const VERSION: u16 = 2;
+/// length of the AES-GCM nonce
+pub(crate) const NONCE_LEN: usize = 12;
+/// upper bounds of the KDF costs, so that a crafted header can not make
+/// opening a vault take all memory or forever. The memory is at most 1 GiB
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 64;
+const MAX_P_COST: u32 = 64;
+
+/// # KDF Parameters
+/// The Argon2id costs the key of a vault is derived with. They are stored in
+/// the header, so that they can be raised without breaking existing vaults.
+///
+/// - `m_cost`: memory in KiB
+/// - `t_cost`: number of iterations
+/// - `p_cost`: degree of parallelism
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct KdfParams {
+    pub(crate) m_cost: u32,
+    pub(crate) t_cost: u32,
+    pub(crate) p_cost: u32,
+}
+
+impl Default for KdfParams {
+    /// the defaults of `Argon2::default()`, which v0 vaults were created with
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    /// checks that the costs are within the upper bounds
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.m_cost > MAX_M_COST || self.t_cost > MAX_T_COST || self.p_cost > MAX_P_COST {
+            return Err(format!(
+                "The key derivation costs are too high (at most {MAX_M_COST} KiB of memory, \
+                 {MAX_T_COST} iterations and {MAX_P_COST} lanes)"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// # Header
+/// The unencrypted beginning of a vault file. It is authenticated as the
+/// associated data of the sealed body, so it can not be changed either.
+///
+/// ```text
+/// magic     4 bytes   "RVLT"
+/// version   u16
+/// m_cost    u32
+/// t_cost    u32
+/// p_cost    u32
+/// salt      u8 length + bytes
+/// nonce     12 bytes
+/// ```
+///
+/// The header is followed by the body sealed with AES-256-GCM. All integers
+/// are little endian.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Header {
+    pub(crate) version: u16,
+    pub(crate) kdf: KdfParams,
+    pub(crate) salt: Vec<u8>,
+    pub(crate) nonce: [u8; NONCE_LEN],
+}
+
+impl Header {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&self.version.to_le_bytes());
+        data.extend_from_slice(&self.kdf.m_cost.to_le_bytes());
+        data.extend_from_slice(&self.kdf.t_cost.to_le_bytes());
+        data.extend_from_slice(&self.kdf.p_cost.to_le_bytes());
+        data.push(self.salt.len() as u8);
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&self.nonce);
+        data
+    }
+
+    /// parses the header at the beginning of a vault file and returns it
+    /// along with the sealed body
+    pub(crate) fn parse(data: &[u8]) -> Result<(Self, &[u8]), String> {
+        let mut reader = Reader::new(data);
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err("This is not a vault file".to_owned());
+        }
+        let version = reader.u16()?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(format!(
+                "The vault has version {version}, which is not supported"
+            ));
+        }
+        let kdf = KdfParams {
+            m_cost: reader.u32()?,
+            t_cost: reader.u32()?,
+            p_cost: reader.u32()?,
+        };
+        kdf.validate()?;
+        let salt_len = reader.u8()? as usize;
+        let salt = reader.bytes(salt_len)?.to_vec();
+        let nonce = reader.bytes(NONCE_LEN)?.try_into().unwrap();
+        let header = Self {
+            version,
+            kdf,
+            salt,
+            nonce,
+        };
+        Ok((header, reader.rest()))
+    }
+}
+
+/// tells whether the file uses the hex format of version 0
+pub(crate) fn is_v0(data: &[u8]) -> bool {
+    !data.starts_with(MAGIC)
+}
+
+/// # Body
+/// Encodes the name of the vault and every credential, which is sealed as a
+/// whole so that no credential can be removed, added or swapped unnoticed.
+///
+/// ```text
+/// name      u32 length + bytes
+/// count     u32
+/// entries   count × (u32 length + key, u32 length + encrypted entry)
+/// ```
+pub(crate) fn encode_body(name: &str, credentials: &HashMap<String, Vec<u8>>) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_field(&mut data, name.as_bytes());
+    data.extend_from_slice(&(credentials.len() as u32).to_le_bytes());
+    let mut keys: Vec<&String> = credentials.keys().collect();
+    keys.sort();
+    for key in keys {
+        write_field(&mut data, key.as_bytes());
+        write_field(&mut data, &credentials[key]);
+    }
+    data
+}
+
+/// decodes the name and the credentials of an opened body
+pub(crate) fn decode_body(data: &[u8]) -> Result<(String, HashMap<String, Vec<u8>>), String> {
+    let mut reader = Reader::new(data);
+    let name = reader.string()?;
+    let count = reader.u32()?;
+    let mut credentials = HashMap::new();
+    for _ in 0..count {
+        let key = reader.string()?;
+        let value = reader.field()?.to_vec();
+        credentials.insert(key, value);
+    }
+    if !reader.rest().is_empty() {
+        return Err("The vault is corrupted".to_owned());
+    }
+    Ok((name, credentials))
+}
+
+fn write_field(data: &mut Vec<u8>, field: &[u8]) {
+    data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+    data.extend_from_slice(field);
+}
+
+/// reads the fields of a vault file one after another
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.data.len() < len {
+            return Err("The vault is corrupted".to_owned());
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn field(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        String::from_utf8(self.field()?.to_vec()).map_err(|_| "The vault is corrupted".to_owned())
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header() {
+        let header = Header {
+            version: VERSION,
+            kdf: KdfParams {
+                m_cost: 65536,
+                t_cost: 3,
+                p_cost: 4,
+            },
+            salt: vec![7; 20],
+            nonce: [9; NONCE_LEN],
+        };
+        let mut data = header.to_bytes();
+        data.extend_from_slice(b"sealed");
+        assert!(!is_v0(&data));
+        assert_eq!(
+            Header::parse(&data).unwrap(),
+            (header.clone(), &b"sealed"[..])
+        );
+
+        // a crafted header can not ask for 4 billion iterations
+        let mut costly = header.clone();
+        costly.kdf.t_cost = u32::MAX;
+        assert!(Header::parse(&costly.to_bytes()).is_err());
+        // nor for more than 1 GiB of memory before the password is checked
+        let mut costly = header.clone();
+        costly.kdf.m_cost = MAX_M_COST + 1;
+        assert!(Header::parse(&costly.to_bytes()).is_err());
+        costly.kdf.m_cost = 4 * 1024 * 1024;
+        assert!(Header::parse(&costly.to_bytes()).is_err());
+        costly.kdf.m_cost = MAX_M_COST;
+        assert!(Header::parse(&costly.to_bytes()).is_ok());
+
+        assert!(Header::parse(&data[..10]).is_err());
+        data[4] = VERSION as u8 + 1;
+        assert!(Header::parse(&data).is_err());
+        assert!(is_v0(b"0a1b2c\n3d4e"));
+    }
+
+    #[test]
+    fn test_body() {
+        let credentials = HashMap::from([
+            ("db".to_owned(), b"secret".to_vec()),
+            ("api".to_owned(), Vec::new()),
+        ]);
+        let body = encode_body("team", &credentials);
+        assert_eq!(
+            decode_body(&body).unwrap(),
+            ("team".to_owned(), credentials)
+        );
+        assert!(decode_body(&body[..body.len() - 1]).is_err());
+    }
+}
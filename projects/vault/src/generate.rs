@@ -0,0 +1,167 @@
+/// lowercase letters
+pub(crate) const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+/// uppercase letters
+pub(crate) const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+/// decimal digits
+pub(crate) const DIGITS: &str = "0123456789";
+/// symbols that can be typed on most keyboard layouts
+pub(crate) const SYMBOLS: &str = "!#$%&*+-./:;<=>?@^_~";
+/// characters that are easily confused with each other
+pub(crate) const AMBIGUOUS: &str = "Il1O0o";
+
+/// the words of passphrases, one per line, 6^4 so that four dice pick one
+pub(crate) const WORDLIST: &str = include_str!("wordlist.txt");
+
+/// # Password Policy
+/// What a generated password is made of.
+///
+/// - `length`: number of characters
+/// - `lower`, `upper`, `digits` and `symbols`: the character classes to use
+/// - `exclude_ambiguous`: leave out characters like `l`, `1` and `O`, `0`
+/// - `every_class`: the password has at least one character of every class
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PasswordPolicy {
+    pub(crate) length: usize,
+    pub(crate) lower: bool,
+    pub(crate) upper: bool,
+    pub(crate) digits: bool,
+    pub(crate) symbols: bool,
+    pub(crate) exclude_ambiguous: bool,
+    pub(crate) every_class: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            length: 20,
+            lower: true,
+            upper: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: false,
+            every_class: true,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// returns the characters of every class that is used, or an error if the
+    /// policy can not be met
+    pub(crate) fn classes(&self) -> Result<Vec<Vec<char>>, String> {
+        let classes: Vec<Vec<char>> = [
+            (self.lower, LOWER),
+            (self.upper, UPPER),
+            (self.digits, DIGITS),
+            (self.symbols, SYMBOLS),
+        ]
+        .into_iter()
+        .filter(|(used, _)| *used)
+        .map(|(_, class)| {
+            class
+                .chars()
+                .filter(|c| !self.exclude_ambiguous || !AMBIGUOUS.contains(*c))
+                .collect()
+        })
+        .collect();
+
+        if classes.is_empty() {
+            return Err("At least one character class is required".to_owned());
+        }
+        if self.length == 0 {
+            return Err("The length of the password has to be at least 1".to_owned());
+        }
+        if self.every_class && self.length < classes.len() {
+            return Err(format!(
+                "A password with every character class needs at least {} characters",
+                classes.len()
+            ));
+        }
+        Ok(classes)
+    }
+
+    /// # Entropy
+    /// Estimates the entropy of the generated passwords in bits, as if every
+    /// character was picked from all classes. Requiring every class lowers
+    /// it a little, which is ignored.
+    pub(crate) fn entropy(&self) -> Result<f64, String> {
+        let pool: usize = self.classes()?.iter().map(Vec::len).sum();
+        Ok(self.length as f64 * (pool as f64).log2())
+    }
+}
+
+/// returns the words of the embedded wordlist
+pub(crate) fn words() -> Vec<&'static str> {
+    WORDLIST.lines().collect()
+}
+
+/// estimates the entropy of a passphrase with the given number of words in bits
+pub(crate) fn passphrase_entropy(words: usize) -> f64 {
+    words as f64 * (self::words().len() as f64).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy() {
+        let policy = PasswordPolicy::default();
+        let classes = policy.classes().unwrap();
+        assert_eq!(classes.len(), 4);
+        assert_eq!(classes.iter().map(Vec::len).sum::<usize>(), 82);
+
+        let policy = PasswordPolicy {
+            exclude_ambiguous: true,
+            symbols: false,
+            ..policy
+        };
+        let pool: Vec<char> = policy.classes().unwrap().concat();
+        assert_eq!(pool.len(), 56);
+        assert!(!pool.iter().any(|c| AMBIGUOUS.contains(*c)));
+
+        let policy = PasswordPolicy {
+            length: 2,
+            ..policy
+        };
+        assert!(policy.classes().is_err());
+        let policy = PasswordPolicy {
+            every_class: false,
+            ..policy
+        };
+        assert!(policy.classes().is_ok());
+        let policy = PasswordPolicy {
+            lower: false,
+            upper: false,
+            digits: false,
+            ..policy
+        };
+        assert!(policy.entropy().is_err());
+
+        let entropy = PasswordPolicy {
+            length: 10,
+            lower: false,
+            upper: false,
+            symbols: false,
+            ..PasswordPolicy::default()
+        }
+        .entropy()
+        .unwrap();
+        assert!((entropy - 10.0 * 10f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_wordlist() {
+        let words = words();
+        assert_eq!(words.len(), 6usize.pow(4));
+        let mut unique = words.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), words.len());
+        assert!(
+            words
+                .iter()
+                .all(|w| w.chars().all(|c| c.is_ascii_lowercase()))
+        );
+        assert!((passphrase_entropy(6) - 62.04).abs() < 0.01);
+    }
+}
@@ -1,7 +1,31 @@
+mod clipboard;
+mod entry;
+mod exchange;
+mod format;
+mod generate;
+mod password;
+#[cfg(test)]
+mod test_dir;
+mod totp;
 mod vault;
+use clap::{ArgGroup, Parser, Subcommand, builder::RangedU64ValueParser};
+use clipboard::{ClearOutcome, LastCopy, Osc52, clear_after};
+use entry::{Entry, FIELDS, SECRET_FIELDS, format_time, now};
+use exchange::{Conflict, Format};
+use format::KdfParams;
+use generate::{PasswordPolicy, passphrase_entropy};
+use password::{PasswordSource, strip_line_end};
 use rpassword::prompt_password;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
 use utils::input;
-use vault::Vault;
+use vault::{Crypto, Vault};
+
+/// seconds until a copied secret is cleared, unless --clear-after says otherwise
+const CLEAR_AFTER: u64 = 45;
 
 fn message_box(message: impl ToString) {
     println!("+{}+", "-".repeat(78));
@@ -9,48 +33,612 @@ fn message_box(message: impl ToString) {
     println!("+{}+", "-".repeat(78));
 }
 
-///
-/// ! to run, execute
-/// * $  cargo run --bin vault
-fn main() {
+/// A password vault that stores encrypted keys and values. Without a command,
+/// it starts an interactive shell.
+#[derive(Debug, Parser)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Read the master password from this file descriptor (e.g. `3` together
+    /// with `3<password.txt`). Without it, the password is read from the file
+    /// in VAULT_PASSWORD_FILE, or from a prompt
+    #[arg(long, global = true)]
+    password_fd: Option<i32>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Create a new vault
+    Create { name: String },
+    /// Copy the password of an entry, or another field with --field, into
+    /// the clipboard, or print it with --reveal
+    #[command(group(ArgGroup::new("output").required(true).args(["clip", "reveal"])))]
+    Get {
+        name: String,
+        key: String,
+        /// title, username, password, url, notes, otp or a custom field
+        #[arg(short, long)]
+        field: Option<String>,
+        /// Copy the value into the clipboard of the terminal (OSC 52)
+        #[arg(long)]
+        clip: bool,
+        /// Seconds until the clipboard is cleared, if it still holds the
+        /// value. 0 keeps it
+        #[arg(long, default_value_t = CLEAR_AFTER, requires = "clip")]
+        clear_after: u64,
+        /// Print the value on the terminal
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Print every field of an entry, except for the password and the TOTP
+    /// secret
+    Show { name: String, key: String },
+    /// Print the current TOTP code of an entry, the secret is set with
+    /// `set <name> <key> --field otp`
+    Otp { name: String, key: String },
+    /// Set the password of an entry, or another field with --field. The entry
+    /// is created if it does not exist. Without a value, it is read from a
+    /// prompt
+    Set {
+        name: String,
+        key: String,
+        value: Option<String>,
+        /// Read the value from stdin
+        #[arg(long, conflicts_with = "value")]
+        stdin: bool,
+        /// title, username, password, url, notes, otp or a custom field
+        #[arg(short, long)]
+        field: Option<String>,
+    },
+    /// Clear a field of an entry, custom fields are removed
+    Unset {
+        name: String,
+        key: String,
+        field: String,
+    },
+    /// Add tags to an entry, or remove them with --remove
+    Tag {
+        name: String,
+        key: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Remove an entry
+    Rm { name: String, key: String },
+    /// List the keys, optionally only those of the entries with a tag
+    List {
+        name: String,
+        #[arg(long)]
+        tag: Option<String>,
+    },
+    /// Import entries from a backup or from another password manager
+    Import {
+        name: String,
+        file: String,
+        #[arg(long, value_enum)]
+        format: Format,
+        /// What happens to entries whose key already exists
+        #[arg(long, value_enum, default_value_t = Conflict::Skip)]
+        conflict: Conflict,
+    },
+    /// Write an encrypted backup of the vault, or with --plaintext, the
+    /// entries in the format of another password manager
+    Export {
+        name: String,
+        file: String,
+        #[arg(long, value_enum, default_value_t = Format::Backup)]
+        format: Format,
+        /// Write every password in plain text, which has to be confirmed
+        #[arg(long)]
+        plaintext: bool,
+        /// Confirm a plain text export without a prompt
+        #[arg(long, requires = "plaintext")]
+        yes: bool,
+    },
+    /// Generate a random password or passphrase and print it along with an
+    /// estimate of its entropy, or store it into a new entry
+    Generate {
+        /// Number of characters, from 8 to 1024
+        #[arg(
+            short,
+            long,
+            default_value_t = 20,
+            value_parser = RangedU64ValueParser::<usize>::new().range(8..=1024)
+        )]
+        length: usize,
+        /// Leave out lowercase letters
+        #[arg(long)]
+        no_lower: bool,
+        /// Leave out uppercase letters
+        #[arg(long)]
+        no_upper: bool,
+        /// Leave out digits
+        #[arg(long)]
+        no_digits: bool,
+        /// Leave out symbols
+        #[arg(long)]
+        no_symbols: bool,
+        /// Leave out characters that are easily confused, like l, 1, O and 0
+        #[arg(long)]
+        exclude_ambiguous: bool,
+        /// Do not require at least one character of every class
+        #[arg(long)]
+        no_every_class: bool,
+        /// Generate a passphrase of random words instead
+        #[arg(long)]
+        passphrase: bool,
+        /// Number of words of the passphrase, from 4 to 64
+        #[arg(
+            long,
+            default_value_t = 6,
+            requires = "passphrase",
+            value_parser = RangedU64ValueParser::<usize>::new().range(4..=64)
+        )]
+        words: usize,
+        /// Separator between the words of the passphrase
+        #[arg(long, default_value = "-", requires = "passphrase")]
+        separator: String,
+        /// Store the result as the password of a new entry in this vault
+        #[arg(long, requires = "key")]
+        vault: Option<String>,
+        /// Key of the new entry
+        #[arg(long, requires = "vault")]
+        key: Option<String>,
+    },
+    /// Change the master password. Every credential is encrypted again with
+    /// a new salt, and the Argon2 costs can be raised along the way
+    Rekey {
+        name: String,
+        /// Read the new password from this file descriptor instead of a
+        /// prompt
+        #[arg(long)]
+        new_password_fd: Option<i32>,
+        /// Raise the Argon2 memory cost to this many KiB
+        #[arg(long)]
+        m_cost: Option<u32>,
+        /// Raise the number of Argon2 iterations to this
+        #[arg(long)]
+        t_cost: Option<u32>,
+    },
+    /// Clear the clipboard after a while, unless something else has been
+    /// copied from the vault since. Run in the background by `get --clip`
+    #[command(hide = true)]
+    ClearClipboard {
+        token: String,
+        #[arg(long)]
+        after: u64,
+        /// The file that holds the token of the last copy
+        #[arg(long)]
+        last_copy: PathBuf,
+    },
+}
+
+/// opens the vault with the password of the given source
+fn open(name: &str, source: &PasswordSource) -> Result<Vault, String> {
+    Vault::open(name.to_owned(), source.read("Enter Password [hidden]: ")?)
+}
+
+/// tells what happened to a copied secret once its time is over
+fn report_cleared(cleared: ClearOutcome) {
+    match cleared {
+        ClearOutcome::Cleared => eprintln!("✅ The clipboard has been cleared"),
+        ClearOutcome::Changed => {
+            eprintln!("The clipboard has changed since, so it was not cleared")
+        }
+        ClearOutcome::Unreadable => {
+            eprintln!("⛔ The terminal does not let the clipboard be read, so it was not cleared")
+        }
+    }
+}
+
+/// writes a plain text export that only the owner can read
+fn write_private(path: &str, content: &str) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| format!("Could not write {path}: {e}"))
+}
+
+/// returns the entry of the given key, which has to exist
+fn entry(vault: &Vault, key: &str) -> Result<Entry, String> {
+    vault
+        .entry(key)?
+        .ok_or_else(|| format!("No credentials found for the key '{key}'"))
+}
+
+/// prints the fields of an entry that have a value, secrets are masked
+fn show(entry: &Entry) {
+    for name in FIELDS {
+        match entry.field(name) {
+            Some("") | None => {}
+            Some(_) if SECRET_FIELDS.contains(&name) => println!("{name:10} *****"),
+            Some(value) => println!("{name:10} {value}"),
+        }
+    }
+    if !entry.tags.is_empty() {
+        let tags: Vec<&str> = entry.tags.iter().map(String::as_str).collect();
+        println!("{:10} {}", "tags", tags.join(", "));
+    }
+    for (name, value) in &entry.fields {
+        println!("{name:10} {value}");
+    }
+    println!("{:10} {}", "created", format_time(entry.created));
+    println!("{:10} {}", "modified", format_time(entry.modified));
+}
+
+/// runs a command without any interaction, except for password prompts
+fn run(command: Command, source: &PasswordSource) -> Result<(), String> {
+    match command {
+        Command::Create { name } => {
+            let password = source.read("Enter password [hidden]: ")?;
+            // a password from a file or a file descriptor is not typed, so it
+            // can not be mistyped either
+            if source.is_prompt() && password != source.read("Confirm password [hidden]: ")? {
+                return Err("Credentials do not match!!".to_owned());
+            }
+            Vault::create(name.clone(), password)?;
+            println!("✅ The vault \"{name}\" has been successfully created");
+        }
+        Command::Get {
+            name,
+            key,
+            field,
+            clip,
+            clear_after,
+            reveal,
+        } => {
+            let entry = entry(&open(&name, source)?, &key)?;
+            let field = field.as_deref().unwrap_or("password");
+            let value = entry
+                .field(field)
+                .ok_or_else(|| format!("The entry '{key}' has no field '{field}'"))?;
+            if reveal {
+                println!("{value}");
+            } else if clip {
+                let last_copy = LastCopy::new();
+                let copied = last_copy.copy(&mut Osc52::new()?, value)?;
+                if clear_after > 0 {
+                    last_copy.clear_later(&copied, Duration::from_secs(clear_after))?;
+                    eprintln!("✅ Copied '{key}', the clipboard is cleared in {clear_after}s");
+                } else {
+                    eprintln!("✅ Copied '{key}'");
+                }
+            }
+        }
+        Command::Show { name, key } => show(&entry(&open(&name, source)?, &key)?),
+        Command::Otp { name, key } => {
+            let totp = entry(&open(&name, source)?, &key)?
+                .totp()?
+                .ok_or_else(|| format!("The entry '{key}' has no TOTP secret"))?;
+            let time = now() as u64;
+            println!("{}", totp.code(time));
+            // like the entropy of `generate`, so that the code can be piped
+            eprintln!("valid for {}s", totp.remaining(time));
+        }
+        Command::Set {
+            name,
+            key,
+            value,
+            stdin,
+            field,
+        } => {
+            let field = field.unwrap_or_else(|| "password".to_owned());
+            let mut vault = open(&name, source)?;
+            let value = match (value, stdin) {
+                (Some(value), _) => value,
+                (None, true) => {
+                    let mut value = String::new();
+                    std::io::stdin()
+                        .read_to_string(&mut value)
+                        .map_err(|e| format!("Could not read stdin: {e}"))?;
+                    strip_line_end(&value).to_owned()
+                }
+                (None, false) if SECRET_FIELDS.contains(&field.as_str()) => {
+                    prompt_password("Value [hidden]: ")
+                        .map_err(|e| format!("Could not read the value: {e}"))?
+                }
+                (None, false) => input(&format!("{field}: ")),
+            };
+            let mut entry = vault.entry(&key)?.unwrap_or_else(|| Entry::new(&key));
+            entry.set_field(&field, value)?;
+            vault.put(key, entry)?;
+        }
+        Command::Unset { name, key, field } => {
+            let mut vault = open(&name, source)?;
+            let mut entry = entry(&vault, &key)?;
+            if !entry.unset_field(&field) {
+                return Err(format!("The entry '{key}' has no field '{field}'"));
+            }
+            vault.put(key, entry)?;
+        }
+        Command::Tag {
+            name,
+            key,
+            tags,
+            remove,
+        } => {
+            let mut vault = open(&name, source)?;
+            let mut entry = entry(&vault, &key)?;
+            for tag in tags {
+                if remove {
+                    entry.tags.remove(&tag);
+                } else {
+                    entry.tags.insert(tag);
+                }
+            }
+            vault.put(key, entry)?;
+        }
+        Command::Rm { name, key } => {
+            if !open(&name, source)?.pop(&key)? {
+                return Err(format!("No credentials found for the key '{key}'"));
+            }
+        }
+        Command::List { name, tag } => {
+            let vault = open(&name, source)?;
+            let keys = match tag {
+                Some(tag) => vault.tagged(&tag)?,
+                None => vault.list(),
+            };
+            keys.iter().for_each(|key| println!("{key}"));
+        }
+        Command::Import {
+            name,
+            file,
+            format,
+            conflict,
+        } => {
+            let password = source.read("Enter Password [hidden]: ")?;
+            let mut vault = Vault::open(name, password.clone())?;
+            let entries = match format {
+                // a backup of the same vault opens with its password, other
+                // backups ask for theirs
+                Format::Backup => match Vault::open_backup(&file, &password) {
+                    Err(_) if source.is_prompt() => {
+                        let password =
+                            prompt_password("Enter the password of the backup [hidden]: ")
+                                .map_err(|e| format!("Could not read the password: {e}"))?;
+                        Vault::open_backup(&file, &password)?
+                    }
+                    backup => backup?,
+                }
+                .entries()?,
+                format => {
+                    let content = std::fs::read_to_string(&file)
+                        .map_err(|e| format!("Could not read {file}: {e}"))?;
+                    exchange::parse(format, &content)?
+                }
+            };
+            let report = vault.import(entries, conflict)?;
+            println!(
+                "✅ Imported {} entries ({} overwritten, {} renamed, {} skipped)",
+                report.added + report.overwritten + report.renamed,
+                report.overwritten,
+                report.renamed,
+                report.skipped
+            );
+        }
+        Command::Export {
+            name,
+            file,
+            format,
+            plaintext,
+            yes,
+        } => {
+            if format == Format::Backup && plaintext {
+                return Err("A backup is always encrypted, pick another --format".to_owned());
+            }
+            if format != Format::Backup && !plaintext {
+                return Err(
+                    "This format stores every password in plain text, pass --plaintext".to_owned(),
+                );
+            }
+            let vault = open(&name, source)?;
+            if format == Format::Backup {
+                vault.backup(&file)?;
+                println!("✅ The vault \"{name}\" has been backed up to {file}");
+                return Ok(());
+            }
+
+            if !yes && input("Type 'yes' to write every password in plain text: ") != "yes" {
+                return Err("The export has been cancelled".to_owned());
+            }
+            let content = exchange::write(format, &vault.entries()?)?;
+            write_private(&file, &content)?;
+            println!("✅ The vault \"{name}\" has been exported to {file}");
+        }
+        Command::Generate {
+            length,
+            no_lower,
+            no_upper,
+            no_digits,
+            no_symbols,
+            exclude_ambiguous,
+            no_every_class,
+            passphrase,
+            words,
+            separator,
+            vault,
+            key,
+        } => {
+            let (password, entropy) = if passphrase {
+                (
+                    Crypto::generate_passphrase(words, &separator),
+                    passphrase_entropy(words),
+                )
+            } else {
+                let policy = PasswordPolicy {
+                    length,
+                    lower: !no_lower,
+                    upper: !no_upper,
+                    digits: !no_digits,
+                    symbols: !no_symbols,
+                    exclude_ambiguous,
+                    every_class: !no_every_class,
+                };
+                (Crypto::generate_password(&policy)?, policy.entropy()?)
+            };
+            // the estimate goes to stderr, so that the password can be piped
+            eprintln!("≈ {entropy:.0} bits of entropy");
+            match (vault, key) {
+                (Some(name), Some(key)) => {
+                    let mut vault = open(&name, source)?;
+                    if vault.entry(&key)?.is_some() {
+                        return Err(format!("The key '{key}' already exists"));
+                    }
+                    vault.push(key.clone(), password)?;
+                    println!("✅ The password has been stored as '{key}'");
+                }
+                _ => println!("{password}"),
+            }
+        }
+        Command::Rekey {
+            name,
+            new_password_fd,
+            m_cost,
+            t_cost,
+        } => {
+            let mut vault = open(&name, source)?;
+            let new_source = new_password_fd.map_or(PasswordSource::Prompt, PasswordSource::Fd);
+            let password = new_source.read("Enter new password [hidden]: ")?;
+            if new_source.is_prompt()
+                && password != new_source.read("Confirm new password [hidden]: ")?
+            {
+                return Err("Credentials do not match!!".to_owned());
+            }
+            let kdf = vault.kdf();
+            vault.rekey(
+                &password,
+                KdfParams {
+                    m_cost: m_cost.unwrap_or(kdf.m_cost),
+                    t_cost: t_cost.unwrap_or(kdf.t_cost),
+                    ..kdf
+                },
+            )?;
+            println!("✅ The vault \"{name}\" has been re-encrypted with the new password");
+        }
+        Command::ClearClipboard {
+            token,
+            after,
+            last_copy,
+        } => {
+            clear_after(&LastCopy::at(last_copy), token, Duration::from_secs(after))?;
+        }
+    }
+    Ok(())
+}
+
+/// the interactive shell
+fn interactive() {
     loop {
         message_box("open | create | exit");
 
         match input("🔒: ").split(" ").collect::<Vec<&str>>().as_slice() {
-            &["create"] => {
-                Vault::create(input("Vault Name: "));
+            ["create"] => {
+                let name = input("Vault Name: ");
+                let passwords = PasswordSource::Prompt
+                    .read("Enter password [hidden]: ")
+                    .and_then(|password| {
+                        let re_password =
+                            PasswordSource::Prompt.read("Confirm password [hidden]: ")?;
+                        Ok((password, re_password))
+                    });
+                let (password, re_password) = match passwords {
+                    Ok(passwords) => passwords,
+                    Err(e) => {
+                        println!("⛔ {e}");
+                        continue;
+                    }
+                };
+                if password != re_password {
+                    println!("⛔ Credentials do not match!!");
+                    continue;
+                }
+                match Vault::create(name.clone(), password) {
+                    Ok(_) => println!("✅ The vault \"{name}\" has been successfully created"),
+                    Err(e) => println!("⛔ {e}"),
+                }
             }
-            &["open"] => {
+            ["open"] => {
                 // println!("{}",);
                 let name = input("Enter Vault Name: ");
-                let password = prompt_password("Enter Password [hidden]: ").unwrap();
+                let password = match PasswordSource::Prompt.read("Enter Password [hidden]: ") {
+                    Ok(password) => password,
+                    Err(e) => {
+                        println!("⛔ {e}");
+                        continue;
+                    }
+                };
                 match Vault::open(name, password) {
                     Ok(mut vault) => {
                         println!("✅ The vault is unlocked");
+                        // the secret that was copied last, which is cleared on lock
+                        let last_copy = LastCopy::new();
+                        let mut copied = None;
                         loop {
-                            message_box("list | get <key> | push <key> <val> | pop <key> | lock");
-                            match input(format!("[ {} ] 🔓: ", vault.name.clone()).as_str())
-                                .split(" ")
-                                .collect::<Vec<&str>>()
-                                .as_slice()
-                            {
-                                &["lock" | "exit" | "close", ..] => {
-                                    println!("⛔ The vault is now locked");
-                                    break;
-                                }
-                                &["get", k] => {
-                                    vault.get(k.to_owned());
-                                }
-                                &["list"] => {
-                                    vault.list();
-                                }
-                                &["push", k, v] => {
-                                    vault.push(k.to_owned(), v.to_owned());
-                                }
-                                &["pop", k] => {
-                                    vault.pop(k.to_owned());
-                                }
-                                _ => {}
+                            message_box(
+                                "list | get <key> [--reveal] | push <key> <val> | pop <key> | lock",
+                            );
+                            let result =
+                                // the value of `push` is the rest of the line, spaces included
+                                match input(format!("[ {} ] 🔓: ", vault.name.clone()).as_str())
+                                    .splitn(3, " ")
+                                    .collect::<Vec<&str>>()
+                                    .as_slice()
+                                {
+                                    ["lock" | "exit" | "close", ..] => {
+                                        if let Some(copied) = copied.take() {
+                                            match Osc52::new().and_then(|mut clipboard| {
+                                                last_copy.clear_if_unchanged(&mut clipboard, &copied)
+                                            }) {
+                                                Ok(cleared) => report_cleared(cleared),
+                                                Err(e) => println!("⛔ {e}"),
+                                            }
+                                        }
+                                        println!("⛔ The vault is now locked");
+                                        break;
+                                    }
+                                    ["get", k, "--reveal"] => vault.get(k).map(|value| match value {
+                                        Some(value) => message_box(format!(
+                                            "the credential for the given key is : {value}"
+                                        )),
+                                        None => println!("No credentials found for the given key"),
+                                    }),
+                                    ["get", k] => vault.get(k).and_then(|value| match value {
+                                        Some(value) => {
+                                            let copy = last_copy.copy(&mut Osc52::new()?, &value)?;
+                                            last_copy
+                                                .clear_later(&copy, Duration::from_secs(CLEAR_AFTER))?;
+                                            copied = Some(copy);
+                                            println!(
+                                                "✅ Copied, the clipboard is cleared in {CLEAR_AFTER}s or on lock"
+                                            );
+                                            Ok(())
+                                        }
+                                        None => {
+                                            println!("No credentials found for the given key");
+                                            Ok(())
+                                        }
+                                    }),
+                                    ["list"] => {
+                                        vault.list().iter().for_each(|k| {
+                                            println!("| {:30} | {:43} |", k, "*****")
+                                        });
+                                        Ok(())
+                                    }
+                                    ["push", k, v] => vault.push(k.to_string(), v.to_string()),
+                                    ["pop", k] => vault.pop(k).map(|_| ()),
+                                    _ => Ok(()),
+                                };
+                            if let Err(e) = result {
+                                println!("⛔ {e}");
                             }
                         }
                     }
@@ -59,10 +647,28 @@ fn main() {
                     }
                 };
             }
-            &["exit"] => {
+            ["exit"] => {
                 break;
             }
             _ => {}
         };
     }
 }
+
+///
+/// ! to run, execute
+/// * $  cargo run --bin vault
+/// * $  cargo run --bin vault -- get <name> <key> --clip
+fn main() {
+    let args = Args::parse();
+    match args.command {
+        Some(command) => {
+            let source = PasswordSource::new(args.password_fd);
+            if let Err(e) = run(command, &source) {
+                eprintln!("⛔ {e}");
+                exit(1);
+            }
+        }
+        None => interactive(),
+    }
+}
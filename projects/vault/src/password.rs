@@ -0,0 +1,133 @@
+use rpassword::prompt_password;
+
+/// environment variable with the path of a file that contains the password
+pub(crate) const PASSWORD_FILE_ENV: &str = "VAULT_PASSWORD_FILE";
+
+/// # Password Source
+/// Where the master password comes from, so that scripts and CI can open a
+/// vault without a terminal:
+///
+/// - `Fd`: a file descriptor given with `--password-fd`, e.g. `3<secret.txt`
+/// - `File`: the file named by the `VAULT_PASSWORD_FILE` environment variable
+/// - `Prompt`: a hidden prompt on the terminal
+///
+/// Only the first line of a file or file descriptor is used.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PasswordSource {
+    Fd(i32),
+    File(String),
+    Prompt,
+}
+
+impl PasswordSource {
+    /// picks the file descriptor, then the environment variable, then the prompt
+    pub(crate) fn new(fd: Option<i32>) -> Self {
+        match (fd, std::env::var(PASSWORD_FILE_ENV)) {
+            (Some(fd), _) => Self::Fd(fd),
+            (None, Ok(path)) if !path.is_empty() => Self::File(path),
+            _ => Self::Prompt,
+        }
+    }
+
+    pub(crate) fn is_prompt(&self) -> bool {
+        *self == Self::Prompt
+    }
+
+    /// reads the password, the prompt is only shown on the terminal
+    pub(crate) fn read(&self, prompt: &str) -> Result<String, String> {
+        let content = match self {
+            Self::Fd(fd) => read_fd(*fd)
+                .map_err(|e| format!("Could not read the password from fd {fd}: {e}"))?,
+            Self::File(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("Could not read the password file {path}: {e}"))?,
+            Self::Prompt => {
+                return prompt_password(prompt)
+                    .map_err(|e| format!("Could not read the password: {e}"));
+            }
+        };
+        Ok(first_line(&content))
+    }
+}
+
+/// Reads everything from a file descriptor that was inherited from the
+/// caller. The descriptor is left open, as it is not owned by the vault.
+#[cfg(unix)]
+fn read_fd(fd: i32) -> std::io::Result<String> {
+    use std::{fs::File, io::Read, mem::ManuallyDrop, os::fd::FromRawFd};
+
+    // SAFETY: `fcntl` only queries the flags of the descriptor
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: the descriptor is open, and it is never closed by the file
+    let mut file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32) -> std::io::Result<String> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "file descriptors are only supported on unix",
+    ))
+}
+
+/// returns the first line without its line ending
+fn first_line(content: &str) -> String {
+    content.lines().next().unwrap_or_default().to_owned()
+}
+
+/// returns the value without one trailing line ending, `\n` or `\r\n`, which
+/// `echo`, heredocs and files edited on Windows end with
+pub(crate) fn strip_line_end(value: &str) -> &str {
+    value
+        .strip_suffix("\r\n")
+        .or_else(|| value.strip_suffix('\n'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_file() {
+        assert_eq!(first_line("hunter2\n"), "hunter2");
+        assert_eq!(first_line("hunter2\r\nignored\n"), "hunter2");
+        assert_eq!(first_line(""), "");
+
+        let path = std::env::temp_dir().join(format!("vault-password-{}", std::process::id()));
+        std::fs::write(&path, "correct horse battery staple\n").unwrap();
+        let source = PasswordSource::File(path.to_string_lossy().to_string());
+        assert_eq!(source.read("").unwrap(), "correct horse battery staple");
+        std::fs::remove_file(&path).unwrap();
+        assert!(source.read("").is_err());
+    }
+
+    #[test]
+    fn test_strip_line_end() {
+        assert_eq!(strip_line_end("x\n"), "x");
+        assert_eq!(strip_line_end("x\r\n"), "x");
+        assert_eq!(strip_line_end("x\n\n"), "x\n");
+        assert_eq!(strip_line_end("x\r"), "x\r");
+        assert_eq!(strip_line_end("x"), "x");
+        assert_eq!(strip_line_end(""), "");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_password_fd() {
+        use std::os::fd::AsRawFd;
+
+        let path = std::env::temp_dir().join(format!("vault-password-fd-{}", std::process::id()));
+        std::fs::write(&path, "hunter2\n").unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        let source = PasswordSource::Fd(file.as_raw_fd());
+        assert_eq!(source.read("").unwrap(), "hunter2");
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+        assert!(PasswordSource::Fd(-1).read("").is_err());
+    }
+}
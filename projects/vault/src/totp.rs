@@ -0,0 +1,222 @@
+use hmac::{Mac, SimpleHmac};
+use sha1::Sha1;
+use sha2::digest::{Digest, core_api::BlockSizeUser};
+use sha2::{Sha256, Sha512};
+use url::Url;
+
+/// the hash function the codes are derived with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// # TOTP
+/// A time-based one-time password generator as described in RFC 6238: the
+/// code is derived from the secret and the number of periods since the unix
+/// epoch.
+///
+/// - `secret`: the shared secret
+/// - `algorithm`: the hash function of the HMAC
+/// - `digits`: number of digits of the code, 6 to 8
+/// - `period`: number of seconds a code is valid for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Totp {
+    pub(crate) secret: Vec<u8>,
+    pub(crate) algorithm: Algorithm,
+    pub(crate) digits: u32,
+    pub(crate) period: u64,
+}
+
+impl Totp {
+    /// # Parse
+    /// Reads an `otpauth://totp/...` URI as shown by QR codes, or a plain
+    /// base32 secret, which uses SHA1, 6 digits and 30 seconds.
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if !value.starts_with("otpauth://") {
+            return Ok(Self {
+                secret: base32_decode(value)?,
+                algorithm: Algorithm::Sha1,
+                digits: 6,
+                period: 30,
+            });
+        }
+
+        let url = Url::parse(value).map_err(|e| format!("Invalid otpauth URI: {e}"))?;
+        if url.host_str() != Some("totp") {
+            return Err("Only time-based (totp) one-time passwords are supported".to_owned());
+        }
+        let mut totp = Self {
+            secret: Vec::new(),
+            algorithm: Algorithm::Sha1,
+            digits: 6,
+            period: 30,
+        };
+        for (name, value) in url.query_pairs() {
+            match name.to_ascii_lowercase().as_str() {
+                "secret" => totp.secret = base32_decode(&value)?,
+                "algorithm" => {
+                    totp.algorithm = match value.to_ascii_uppercase().as_str() {
+                        "SHA1" => Algorithm::Sha1,
+                        "SHA256" => Algorithm::Sha256,
+                        "SHA512" => Algorithm::Sha512,
+                        _ => return Err(format!("Unsupported algorithm {value}")),
+                    }
+                }
+                "digits" => {
+                    totp.digits = value
+                        .parse()
+                        .ok()
+                        .filter(|digits| (6..=8).contains(digits))
+                        .ok_or_else(|| format!("Unsupported number of digits {value}"))?
+                }
+                "period" => {
+                    totp.period = value
+                        .parse()
+                        .ok()
+                        .filter(|period| *period > 0)
+                        .ok_or_else(|| format!("Invalid period {value}"))?
+                }
+                // issuer, image and other parameters only matter to apps
+                _ => {}
+            }
+        }
+        if totp.secret.is_empty() {
+            return Err("The otpauth URI has no secret".to_owned());
+        }
+        Ok(totp)
+    }
+
+    /// returns the code at the given unix time
+    pub(crate) fn code(&self, time: u64) -> String {
+        let counter = (time / self.period).to_be_bytes();
+        let hash = match self.algorithm {
+            Algorithm::Sha1 => hmac::<Sha1>(&self.secret, &counter),
+            Algorithm::Sha256 => hmac::<Sha256>(&self.secret, &counter),
+            Algorithm::Sha512 => hmac::<Sha512>(&self.secret, &counter),
+        };
+        // dynamic truncation of RFC 4226
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+        let code = binary % 10u32.pow(self.digits);
+        format!("{code:0width$}", width = self.digits as usize)
+    }
+
+    /// returns the number of seconds the code at the given unix time is still
+    /// valid for
+    pub(crate) fn remaining(&self, time: u64) -> u64 {
+        self.period - time % self.period
+    }
+}
+
+/// HMAC of RFC 2104 with the given hash function
+fn hmac<D: Digest + BlockSizeUser>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    SimpleHmac::<D>::new_from_slice(key)
+        .expect("HMAC takes keys of any length")
+        .chain_update(message)
+        .finalize()
+        .into_bytes()
+        .to_vec()
+}
+
+/// decodes base32 of RFC 4648, ignoring case, spaces and padding
+fn base32_decode(value: &str) -> Result<Vec<u8>, String> {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut data = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in value.bytes().filter(|c| !matches!(c, b' ' | b'-' | b'=')) {
+        let index = ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())
+            .ok_or_else(|| format!("'{}' is not a base32 character", c as char))?;
+        buffer = (buffer << 5) | index as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    if data.is_empty() {
+        return Err("The TOTP secret is empty".to_owned());
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the test vectors of appendix B of RFC 6238
+    #[test]
+    fn test_rfc_vectors() {
+        let times = [
+            59,
+            1111111109,
+            1111111111,
+            1234567890,
+            2000000000,
+            20000000000,
+        ];
+        let vectors = [
+            (
+                Algorithm::Sha1,
+                &b"12345678901234567890"[..],
+                [
+                    "94287082", "07081804", "14050471", "89005924", "69279037", "65353130",
+                ],
+            ),
+            (
+                Algorithm::Sha256,
+                b"12345678901234567890123456789012",
+                [
+                    "46119246", "68084774", "67062674", "91819424", "90698825", "77737706",
+                ],
+            ),
+            (
+                Algorithm::Sha512,
+                b"1234567890123456789012345678901234567890123456789012345678901234",
+                [
+                    "90693936", "25091201", "99943326", "93441116", "38618901", "47863826",
+                ],
+            ),
+        ];
+        for (algorithm, secret, codes) in vectors {
+            let totp = Totp {
+                secret: secret.to_vec(),
+                algorithm,
+                digits: 8,
+                period: 30,
+            };
+            for (time, code) in times.iter().zip(codes) {
+                assert_eq!(totp.code(*time), code, "{algorithm:?} at {time}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let totp = Totp::parse("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap();
+        assert_eq!(totp.secret, b"12345678901234567890");
+        assert_eq!(totp.code(59), "287082");
+        assert_eq!(totp.remaining(59), 1);
+
+        let totp = Totp::parse(
+            "otpauth://totp/ACME%20Co:john@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60",
+        )
+        .unwrap();
+        assert_eq!(
+            (totp.algorithm, totp.digits, totp.period),
+            (Algorithm::Sha256, 8, 60)
+        );
+        assert_eq!(totp.remaining(61), 59);
+
+        assert!(Totp::parse("otpauth://hotp/x?secret=GEZDGNBV&counter=1").is_err());
+        assert!(Totp::parse("otpauth://totp/x?secret=GEZDGNBV&digits=5").is_err());
+        assert!(Totp::parse("otpauth://totp/x?issuer=x").is_err());
+        assert!(Totp::parse("not base32!").is_err());
+    }
+}
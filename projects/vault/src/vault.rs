@@ -1,17 +1,21 @@
-use argon2::Argon2;
+use argon2::{Algorithm, Argon2, Params, Version};
 use rand::Rng;
-use rpassword::prompt_password;
-use std::io::{Read, Write};
-use std::{collections::HashMap, fs::OpenOptions};
+use rand::seq::{IndexedRandom, SliceRandom};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
 
 use aes_gcm::{
     Aes256Gcm, Key, Nonce,
-    aead::{Aead, AeadCore, KeyInit, OsRng},
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
 };
 
-use crate::message_box;
+use crate::entry::{Entry, now};
+use crate::exchange::{Conflict, ImportReport};
+use crate::format::{self, Header, KdfParams, NONCE_LEN, VERSION};
+use crate::generate::{self, PasswordPolicy};
 
-struct Crypto;
+pub(crate) struct Crypto;
 
 impl Crypto {
     pub(crate) fn generate_salt(length: usize) -> Vec<u8> {
@@ -19,13 +23,48 @@ impl Crypto {
         (0..length).map(|_| rng.random()).collect()
     }
 
-    pub(crate) fn generate_key(password: String, salt: &Vec<u8>) -> [u8; 32] {
-        let mut key = [0u8; 32]; // Can be any desired size
-        match Argon2::default().hash_password_into(password.as_bytes(), salt, &mut key) {
-            Ok(data) => println!("{data:?}"),
-            Err(error) => println!("{error:?}"),
+    /// # Generate Password
+    /// Picks every character of the password at random from the classes of
+    /// the policy. If every class is required, one character of each class
+    /// is picked first and the characters are shuffled afterwards.
+    pub(crate) fn generate_password(policy: &PasswordPolicy) -> Result<String, String> {
+        let classes = policy.classes()?;
+        let pool = classes.concat();
+        let mut rng = rand::rng();
+
+        let mut password: Vec<char> = Vec::with_capacity(policy.length);
+        if policy.every_class {
+            password.extend(classes.iter().filter_map(|class| class.choose(&mut rng)));
+        }
+        while password.len() < policy.length {
+            password.extend(pool.choose(&mut rng));
         }
-        key
+        password.shuffle(&mut rng);
+        Ok(password.into_iter().collect())
+    }
+
+    /// generates a passphrase of random words of the embedded wordlist
+    pub(crate) fn generate_passphrase(words: usize, separator: &str) -> String {
+        let mut rng = rand::rng();
+        let wordlist = generate::words();
+        (0..words)
+            .filter_map(|_| wordlist.choose(&mut rng).copied())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    pub(crate) fn generate_key(
+        password: &str,
+        salt: &[u8],
+        kdf: &KdfParams,
+    ) -> Result<[u8; 32], String> {
+        let mut key = [0u8; 32]; // Can be any desired size
+        let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(key.len()))
+            .map_err(|e| format!("Invalid key derivation parameters: {e}"))?;
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), salt, &mut key)
+            .map_err(|e| format!("Could not derive the key: {e}"))?;
+        Ok(key)
     }
     pub(crate) fn encrypt(key: [u8; 32], message: String) -> Vec<u8> {
         let key_array = Key::<Aes256Gcm>::from_slice(&key);
@@ -41,6 +80,9 @@ impl Crypto {
         encrypted_data
     }
     pub(crate) fn decrypt(key: [u8; 32], encrypted_data: Vec<u8>) -> Result<String, String> {
+        if encrypted_data.len() < 12 {
+            return Err("The vault is corrupted".to_owned());
+        }
         let key = Key::<Aes256Gcm>::from_slice(&key);
         let (nonce_arr, ciphered_data) = encrypted_data.split_at(12);
         let nonce = Nonce::from_slice(nonce_arr);
@@ -50,170 +92,605 @@ impl Crypto {
             .map_err(|_| "Invalid credentials".to_owned())?;
         String::from_utf8(plaintext).map_err(|_| "The vault is corrupted".to_owned())
     }
+
+    /// encrypts the message with the given nonce and authenticates the
+    /// associated data along with it
+    pub(crate) fn seal(
+        key: [u8; 32],
+        nonce: &[u8; NONCE_LEN],
+        message: &[u8],
+        aad: &[u8],
+    ) -> Vec<u8> {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .encrypt(Nonce::from_slice(nonce), Payload { msg: message, aad })
+            .expect("failed to encrypt")
+    }
+
+    /// decrypts a message sealed with `seal`, it fails if the message or the
+    /// associated data were changed
+    pub(crate) fn open(
+        key: [u8; 32],
+        nonce: &[u8; NONCE_LEN],
+        sealed: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: sealed, aad })
+            .map_err(|_| "Invalid credentials or the vault was tampered with".to_owned())
+    }
+
+    pub(crate) fn generate_nonce() -> [u8; NONCE_LEN] {
+        Aes256Gcm::generate_nonce(&mut OsRng).into()
+    }
 }
 
 #[derive(Clone)]
 pub(crate) struct Vault {
     pub(crate) name: String,
     salt: Vec<u8>,
+    kdf: KdfParams,
     key: [u8; 32],
     credentials: HashMap<String, Vec<u8>>,
 }
 
+/// decodes a hex field of the vault file
+fn decode(field: &str) -> Result<Vec<u8>, String> {
+    hex::decode(field).map_err(|_| "The vault is corrupted".to_owned())
+}
+
 impl Vault {
     /// Create a new Vault
     ///
     /// the `new` method creates a new instance of `Vault` with the given name
     /// and an empty list of credentials
-    pub(crate) fn new(name: String, key: [u8; 32], salt: Vec<u8>) -> Self {
+    pub(crate) fn new(name: String, key: [u8; 32], salt: Vec<u8>, kdf: KdfParams) -> Self {
         Self {
             name,
             key,
             salt,
+            kdf,
             credentials: HashMap::new(),
         }
     }
 
-    pub(crate) fn list(&mut self) {
-        self.credentials
-            .clone()
-            .keys()
-            .for_each(|k| println!("| {:30} | {:43} |", k, "*****"));
+    /// returns the path of the vault file
+    fn path(name: &str) -> String {
+        format!("{name}.vault")
     }
 
-    pub(crate) fn get(&mut self, key: String) {
-        match self.credentials.get(&key).as_ref() {
-            Some(&value) => {
-                let decrypted = Crypto::decrypt(self.key, value.clone()).unwrap();
-                message_box(format!("the credential for the given key is : {decrypted}"));
-            }
-            None => {
-                println!("No credentials found for the given key")
+    /// returns the keys of all credentials in alphabetical order
+    pub(crate) fn list(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.credentials.keys().map(String::as_str).collect();
+        keys.sort();
+        keys
+    }
+
+    /// returns the keys of the entries with the given tag in alphabetical order
+    pub(crate) fn tagged(&self, tag: &str) -> Result<Vec<&str>, String> {
+        let mut keys = Vec::new();
+        for key in self.list() {
+            if self.entry(key)?.is_some_and(|entry| entry.has_tag(tag)) {
+                keys.push(key);
             }
-        };
+        }
+        Ok(keys)
+    }
+
+    /// returns the decrypted entry of the given key, if there is any
+    pub(crate) fn entry(&self, key: &str) -> Result<Option<Entry>, String> {
+        self.credentials
+            .get(key)
+            .map(|value| {
+                let json = Crypto::decrypt(self.key, value.clone())?;
+                serde_json::from_str(&json).map_err(|_| "The vault is corrupted".to_owned())
+            })
+            .transpose()
     }
 
-    pub(crate) fn push(&mut self, key: String, value: String) {
-        let encoded = Crypto::encrypt(self.key, value);
-        self.credentials.insert(key, encoded);
-        self.save(false);
+    /// returns every entry along with its key in alphabetical order
+    pub(crate) fn entries(&self) -> Result<Vec<(String, Entry)>, String> {
+        self.list()
+            .into_iter()
+            .map(|key| Ok((key.to_owned(), self.entry(key)?.unwrap_or_default())))
+            .collect()
+    }
+
+    /// # Import
+    /// Stores the entries under their keys, keeping their timestamps. A key
+    /// that already exists, also one that an earlier imported entry took, is
+    /// handled by the conflict policy. The vault is saved once at the end.
+    pub(crate) fn import(
+        &mut self,
+        entries: Vec<(String, Entry)>,
+        conflict: Conflict,
+    ) -> Result<ImportReport, String> {
+        let mut report = ImportReport::default();
+        for (key, entry) in entries {
+            let key = if !self.credentials.contains_key(&key) {
+                report.added += 1;
+                key
+            } else {
+                match conflict {
+                    Conflict::Skip => {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    Conflict::Overwrite => {
+                        report.overwritten += 1;
+                        key
+                    }
+                    Conflict::Rename => {
+                        report.renamed += 1;
+                        (2..)
+                            .map(|n| format!("{key} ({n})"))
+                            .find(|key| !self.credentials.contains_key(key))
+                            .unwrap()
+                    }
+                }
+            };
+            self.insert(key, &entry);
+        }
+        self.save()?;
+        Ok(report)
     }
 
-    pub(crate) fn pop(&mut self, key: String) {
-        self.credentials.remove(&key);
-        self.save(false);
+    /// returns the password of the given key, if there is any
+    pub(crate) fn get(&self, key: &str) -> Result<Option<String>, String> {
+        Ok(self.entry(key)?.map(|entry| entry.password))
+    }
+
+    /// stores the entry under the given key and updates its modification time
+    pub(crate) fn put(&mut self, key: String, mut entry: Entry) -> Result<(), String> {
+        entry.modified = now();
+        self.insert(key, &entry);
+        self.save()
+    }
+
+    /// sets the password of the given key, the entry is created if it is missing
+    pub(crate) fn push(&mut self, key: String, value: String) -> Result<(), String> {
+        let mut entry = self.entry(&key)?.unwrap_or_else(|| Entry::new(&key));
+        entry.password = value;
+        self.put(key, entry)
+    }
+
+    /// encrypts the entry on its own, without saving the vault
+    fn insert(&mut self, key: String, entry: &Entry) {
+        let json = serde_json::to_string(entry).expect("failed to serialize the entry");
+        self.credentials
+            .insert(key, Crypto::encrypt(self.key, json));
+    }
+
+    /// turns the plain passwords that vaults stored before version 2 into
+    /// entries
+    fn upgrade_entries(&mut self) -> Result<(), String> {
+        for (key, value) in self.credentials.clone() {
+            let mut entry = Entry::new(&key);
+            entry.password = Crypto::decrypt(self.key, value)?;
+            self.insert(key, &entry);
+        }
+        Ok(())
+    }
+
+    /// removes the credential of the given key and returns whether it existed
+    pub(crate) fn pop(&mut self, key: &str) -> Result<bool, String> {
+        if self.credentials.remove(key).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// returns the Argon2 parameters the key of the vault is derived with
+    pub(crate) fn kdf(&self) -> KdfParams {
+        self.kdf
+    }
+
+    /// # Rekey
+    /// Changes the master password: a new key is derived from the password
+    /// with a new salt and the given parameters, which can only be raised,
+    /// and every credential is encrypted again with it.
+    ///
+    /// The vault is re-encrypted as a copy and only replaced once it was
+    /// saved, the file itself is replaced atomically by `save`.
+    pub(crate) fn rekey(&mut self, password: &str, kdf: KdfParams) -> Result<(), String> {
+        if kdf.m_cost < self.kdf.m_cost
+            || kdf.t_cost < self.kdf.t_cost
+            || kdf.p_cost < self.kdf.p_cost
+        {
+            return Err(format!(
+                "The key derivation costs can only be raised (memory {} KiB, {} iterations, {} lanes)",
+                self.kdf.m_cost, self.kdf.t_cost, self.kdf.p_cost
+            ));
+        }
+        kdf.validate()?;
+        let salt = Crypto::generate_salt(20);
+        let key = Crypto::generate_key(password, &salt, &kdf)?;
+
+        let mut vault = Vault::new(self.name.clone(), key, salt, kdf);
+        for (k, v) in &self.credentials {
+            let value = Crypto::decrypt(self.key, v.clone())?;
+            vault
+                .credentials
+                .insert(k.clone(), Crypto::encrypt(key, value));
+        }
+        vault.save()?;
+        *self = vault;
+        Ok(())
     }
 
     /// Open an existing vault
     ///
-    /// If a vault exists, it opens the vault, else shows an error message saying it doesn't exist.
+    /// If a vault exists, it opens the vault, else returns an error saying it doesn't exist.
+    /// Vaults of older versions, e.g. in the hex format of version 0, are
+    /// converted to the current format once they are opened.
     pub(crate) fn open(name: String, password: String) -> Result<Self, String> {
-        match OpenOptions::new()
-            .read(true)
-            .create_new(false) // This will cause an error if the file already exists
-            .open(format!("{name}.vault"))
-            .as_mut()
-        {
-            Ok(file) => {
-                let mut buf = String::new();
-                file.read_to_string(&mut buf).unwrap();
-                let mut data: Vec<String> = buf.split("\n").map(|s| s.to_string()).collect();
+        let data =
+            fs::read(Self::path(&name)).map_err(|_| "Could not open the vault".to_string())?;
+        if format::is_v0(&data) {
+            let mut vault = Self::open_v0(name, password, &data)?;
+            vault.upgrade_entries()?;
+            vault.save()?;
+            return Ok(vault);
+        }
 
-                let _salt_string = data.remove(0);
-                let _name = data.remove(0);
+        let (mut vault, version) = Self::unseal(&data, &password)?;
+        // just to confirm whether the vault name is correct
+        if vault.name != name {
+            return Err("The vault is corrupt".to_owned());
+        }
+        if version == 1 {
+            vault.upgrade_entries()?;
+            vault.save()?;
+        }
+        Ok(vault)
+    }
 
-                let salt = hex::decode(_salt_string.clone().to_owned()).unwrap();
-                let key = Crypto::generate_key(password.clone(), &salt);
+    /// opens a backup written by `backup`, which keeps the name of the vault
+    /// it was written from
+    pub(crate) fn open_backup(path: &str, password: &str) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| format!("Could not read the backup {path}: {e}"))?;
+        let (mut vault, version) = Self::unseal(&data, password)?;
+        if version == 1 {
+            vault.upgrade_entries()?;
+        }
+        Ok(vault)
+    }
 
-                // just to confirm whether the vault name is correct
-                let decrypted_name = Crypto::decrypt(key, hex::decode(_name).unwrap())?;
-                if decrypted_name != name {
-                    println!("⛔ The vault is corrupt");
-                }
+    /// decrypts a vault file of version 1 or later and returns the vault
+    /// along with the version of the file
+    fn unseal(data: &[u8], password: &str) -> Result<(Self, u16), String> {
+        let (header, sealed) = format::Header::parse(data)?;
+        let key = Crypto::generate_key(password, &header.salt, &header.kdf)?;
+        // the header is authenticated along with the body
+        let aad = &data[..data.len() - sealed.len()];
+        let body = Crypto::open(key, &header.nonce, sealed, aad)?;
+        let (name, credentials) = format::decode_body(&body)?;
 
-                let mut vault = Self::new(name, key, salt);
-                data.iter()
-                    .filter_map(|d| d.split_once('#'))
-                    .for_each(|(k, v)| {
-                        vault.credentials.insert(
-                            Crypto::decrypt(key, hex::decode(k.to_owned()).unwrap()).unwrap(),
-                            hex::decode(v).unwrap(),
-                        );
-                    });
-
-                Ok(vault)
-            }
-            Err(_) => Err("Could not open the vault".to_string()),
+        let mut vault = Self::new(name, key, header.salt, header.kdf);
+        vault.credentials = credentials;
+        Ok((vault, header.version))
+    }
+
+    /// opens a vault in the hex format of version 0: the salt, the encrypted
+    /// name and `key#value` lines, where key and value are encrypted
+    fn open_v0(name: String, password: String, data: &[u8]) -> Result<Self, String> {
+        let buf = String::from_utf8_lossy(data);
+        let mut data = buf.split('\n');
+
+        let salt = decode(data.next().unwrap_or_default())?;
+        // version 0 always used the default parameters
+        let kdf = KdfParams::default();
+        let key = Crypto::generate_key(&password, &salt, &kdf)?;
+
+        // just to confirm whether the vault name is correct
+        let decrypted_name = Crypto::decrypt(key, decode(data.next().unwrap_or_default())?)?;
+        if decrypted_name != name {
+            return Err("The vault is corrupt".to_owned());
+        }
+
+        let mut vault = Self::new(name, key, salt, kdf);
+        for (k, v) in data.filter_map(|d| d.split_once('#')) {
+            vault
+                .credentials
+                .insert(Crypto::decrypt(key, decode(k)?)?, decode(v)?);
         }
+        Ok(vault)
     }
 
     /// Create
     ///
     /// This method creates a new vault with credentials.
     /// The vault contains encrypted credentials along with few encryption data
-    /// such as number of iterations and salt.
-    pub(crate) fn create(name: String) {
-        let file_path = format!("{name}.vault");
+    /// such as the Argon2 parameters and salt.
+    pub(crate) fn create(name: String, password: String) -> Result<Self, String> {
+        Self::create_with(name, password, KdfParams::default())
+    }
+
+    /// creates a new vault whose key is derived with the given parameters
+    pub(crate) fn create_with(
+        name: String,
+        password: String,
+        kdf: KdfParams,
+    ) -> Result<Self, String> {
         // check if file exists
-        match OpenOptions::new()
-            .read(true)
-            .create_new(false)
-            .open(file_path.clone())
-        {
-            Ok(_) => {
-                println!("⛔ The vault with name '{name}' already Exists");
-            }
+        if std::path::Path::new(&Self::path(&name)).exists() {
+            return Err(format!("The vault with name '{name}' already Exists"));
+        }
+        // generate salt
+        let salt = Crypto::generate_salt(20);
+        let key = Crypto::generate_key(&password, &salt, &kdf)?;
 
-            Err(_) => {
-                // create a new vault
-                let password = prompt_password("Enter password [hidden]: ").unwrap();
-                let re_password = prompt_password("Confirm password [hidden]: ").unwrap();
-                if password != re_password {
-                    println!("⛔ Credentials do not match!!");
-                    return;
-                }
-                // generate salt
-                let salt = Crypto::generate_salt(20);
-                let key = Crypto::generate_key(password.clone(), &salt);
+        let mut vault = Vault::new(name, key, salt, kdf);
+        vault.save()?;
+        Ok(vault)
+    }
 
-                let mut vault = Vault::new(name, key, salt);
-                vault.save(true);
-            }
+    /// # Save
+    /// Seals the whole vault with a new nonce and writes it into the vault
+    /// file. The file is written into a temporary file first and then renamed,
+    /// so that a crash never leaves a half written vault behind.
+    fn save(&mut self) -> Result<(), String> {
+        self.write(&Self::path(&self.name))
+    }
+
+    /// # Backup
+    /// Writes the sealed vault into another file, which is encrypted with the
+    /// same master password and can be imported with `open_backup`.
+    pub(crate) fn backup(&self, path: &str) -> Result<(), String> {
+        self.write(path)
+    }
+
+    /// seals the vault and writes it into the given file atomically
+    fn write(&self, file_path: &str) -> Result<(), String> {
+        let header = Header {
+            version: VERSION,
+            kdf: self.kdf,
+            salt: self.salt.clone(),
+            nonce: Crypto::generate_nonce(),
+        };
+        let mut data = header.to_bytes();
+        let body = format::encode_body(&self.name, &self.credentials);
+        let sealed = Crypto::seal(self.key, &header.nonce, &body, &data);
+        data.extend_from_slice(&sealed);
+
+        write_synced(file_path, &data)
+            .map_err(|e| format!("Could not save the vault '{}': {e}", self.name))
+    }
+}
+
+/// Writes the data into a temporary file that is synced to disk and renamed
+/// to the given path. The directory is synced as well, so that a crash or a
+/// power loss leaves either the old or the new file behind.
+fn write_synced(file_path: &str, data: &[u8]) -> std::io::Result<()> {
+    let tmp_path = format!("{file_path}.tmp");
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, file_path)?;
+    // directories can not be opened as files on windows
+    #[cfg(unix)]
+    {
+        let dir = match std::path::Path::new(file_path).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => std::path::Path::new("."),
         };
+        fs::File::open(dir)?.sync_all()?;
     }
+    Ok(())
+}
 
-    fn save(&mut self, create_new: bool) {
-        let file_path = format!("{}.vault", self.name);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TempDir;
 
-        let mut data = Vec::new();
-        data.push(hex::encode(self.salt.clone()));
-        data.push(hex::encode(Crypto::encrypt(self.key, self.name.clone())));
-        self.credentials.iter().for_each(|(k, v)| {
-            data.push(format!(
-                "{}#{}",
-                hex::encode(Crypto::encrypt(self.key, k.clone())),
-                hex::encode(v)
-            ));
-        });
-
-        match OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .create_new(create_new) // This will cause an error if the file already exists
-            .open(file_path)
-            .as_mut()
-        {
-            Ok(file) => {
-                let _ = file.write(data.join("\n").as_bytes());
-                println!(
-                    "✅ The vault \"{}\" has been successfully created",
-                    self.name
-                );
-            }
-            Err(e) => {
-                println!("⛔ Could not save the vault '{}'", self.name);
-                println!("{e}");
+    /// cheap parameters, so that the tests do not spend their time in Argon2
+    const TEST_KDF: KdfParams = KdfParams {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    /// returns the name of a vault inside an empty temporary directory, which
+    /// is removed once the returned guard is dropped
+    fn vault_name(test: &str) -> (TempDir, String) {
+        let dir = TempDir::new(test);
+        let name = dir.join("test").display().to_string();
+        (dir, name)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let (_dir, name) = vault_name("roundtrip");
+        let mut vault = Vault::create_with(name.clone(), "pw".to_owned(), TEST_KDF).unwrap();
+        vault.push("db".to_owned(), "secret".to_owned()).unwrap();
+        vault.push("api".to_owned(), "token".to_owned()).unwrap();
+        assert!(Vault::create(name.clone(), "pw".to_owned()).is_err());
+
+        let mut vault = Vault::open(name.clone(), "pw".to_owned()).unwrap();
+        assert_eq!(vault.kdf, TEST_KDF);
+        assert_eq!(vault.list(), ["api", "db"]);
+        assert_eq!(vault.get("db").unwrap().as_deref(), Some("secret"));
+        assert!(vault.pop("api").unwrap());
+
+        let vault = Vault::open(name.clone(), "pw".to_owned()).unwrap();
+        assert_eq!(vault.list(), ["db"]);
+        assert!(Vault::open(name, "wrong".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_tampering() {
+        let (_dir, name) = vault_name("tampering");
+        let mut vault = Vault::create_with(name.clone(), "pw".to_owned(), TEST_KDF).unwrap();
+        vault.push("db".to_owned(), "secret".to_owned()).unwrap();
+        let path = Vault::path(&name);
+        let data = fs::read(&path).unwrap();
+
+        // a flipped bit in the header or in the body is noticed
+        for index in [6, data.len() - 1] {
+            let mut tampered = data.clone();
+            tampered[index] ^= 1;
+            fs::write(&path, tampered).unwrap();
+            assert!(Vault::open(name.clone(), "pw".to_owned()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let policy = PasswordPolicy {
+            length: 4,
+            exclude_ambiguous: true,
+            ..PasswordPolicy::default()
+        };
+        for _ in 0..100 {
+            let password = Crypto::generate_password(&policy).unwrap();
+            assert_eq!(password.chars().count(), 4);
+            for class in [
+                generate::LOWER,
+                generate::UPPER,
+                generate::DIGITS,
+                generate::SYMBOLS,
+            ] {
+                assert!(password.chars().any(|c| class.contains(c)));
             }
+            assert!(!password.chars().any(|c| generate::AMBIGUOUS.contains(c)));
         }
+        assert!(
+            Crypto::generate_password(&PasswordPolicy {
+                length: 3,
+                ..policy
+            })
+            .is_err()
+        );
+
+        let passphrase = Crypto::generate_passphrase(5, " ");
+        let words = generate::words();
+        assert_eq!(passphrase.split(' ').count(), 5);
+        assert!(passphrase.split(' ').all(|word| words.contains(&word)));
+    }
+
+    #[test]
+    fn test_import() {
+        let (_dir, name) = vault_name("import");
+        let mut vault = Vault::create_with(name.clone(), "pw".to_owned(), TEST_KDF).unwrap();
+        vault.push("db".to_owned(), "old".to_owned()).unwrap();
+        let imported = |password: &str| {
+            let mut entry = Entry::new("db");
+            entry.password = password.to_owned();
+            ("db".to_owned(), entry)
+        };
+
+        let report = vault
+            .import(vec![imported("new"), imported("newer")], Conflict::Skip)
+            .unwrap();
+        assert_eq!((report.added, report.skipped), (0, 2));
+        assert_eq!(vault.get("db").unwrap().as_deref(), Some("old"));
+
+        let report = vault
+            .import(vec![imported("new")], Conflict::Overwrite)
+            .unwrap();
+        assert_eq!(report.overwritten, 1);
+        assert_eq!(vault.get("db").unwrap().as_deref(), Some("new"));
+
+        vault
+            .import(vec![imported("a"), imported("b")], Conflict::Rename)
+            .unwrap();
+        assert_eq!(vault.list(), ["db", "db (2)", "db (3)"]);
+
+        // a backup opens with the same password and keeps the entries
+        let backup = format!("{name}.backup");
+        vault.backup(&backup).unwrap();
+        let restored = Vault::open_backup(&backup, "pw").unwrap();
+        assert_eq!(restored.name, name);
+        assert_eq!(restored.entries().unwrap(), vault.entries().unwrap());
+        assert!(Vault::open_backup(&backup, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_rekey() {
+        let (_dir, name) = vault_name("rekey");
+        let mut vault = Vault::create_with(name.clone(), "old".to_owned(), TEST_KDF).unwrap();
+        vault.push("db".to_owned(), "secret".to_owned()).unwrap();
+        let salt = vault.salt.clone();
+
+        let lower = KdfParams {
+            t_cost: 0,
+            ..TEST_KDF
+        };
+        assert!(vault.rekey("new", lower).is_err());
+        let lower = KdfParams {
+            p_cost: 0,
+            ..TEST_KDF
+        };
+        assert!(vault.rekey("new", lower).is_err());
+        let too_high = KdfParams {
+            m_cost: u32::MAX,
+            ..TEST_KDF
+        };
+        assert!(vault.rekey("new", too_high).is_err());
+        let higher = KdfParams {
+            m_cost: 128,
+            t_cost: 2,
+            ..TEST_KDF
+        };
+        vault.rekey("new", higher).unwrap();
+        assert_ne!(vault.salt, salt);
+
+        assert!(Vault::open(name.clone(), "old".to_owned()).is_err());
+        let vault = Vault::open(name, "new".to_owned()).unwrap();
+        assert_eq!(vault.kdf(), higher);
+        assert_eq!(vault.get("db").unwrap().as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_v0_migration() {
+        let (_dir, name) = vault_name("migration");
+        let salt = Crypto::generate_salt(20);
+        let key = Crypto::generate_key("pw", &salt, &KdfParams::default()).unwrap();
+        let data = format!(
+            "{}\n{}\n{}#{}",
+            hex::encode(&salt),
+            hex::encode(Crypto::encrypt(key, name.clone())),
+            hex::encode(Crypto::encrypt(key, "db".to_owned())),
+            hex::encode(Crypto::encrypt(key, "secret".to_owned())),
+        );
+        fs::write(Vault::path(&name), data).unwrap();
+
+        let vault = Vault::open(name.clone(), "pw".to_owned()).unwrap();
+        assert_eq!(vault.get("db").unwrap().as_deref(), Some("secret"));
+        assert!(!format::is_v0(&fs::read(Vault::path(&name)).unwrap()));
+
+        let vault = Vault::open(name, "pw".to_owned()).unwrap();
+        assert_eq!(vault.kdf, KdfParams::default());
+        let entry = vault.entry("db").unwrap().unwrap();
+        assert_eq!(
+            (entry.title, entry.password),
+            ("db".into(), "secret".into())
+        );
+    }
+
+    #[test]
+    fn test_entries() {
+        let (_dir, name) = vault_name("entries");
+        let mut vault = Vault::create_with(name.clone(), "pw".to_owned(), TEST_KDF).unwrap();
+        vault
+            .push("db".to_owned(), "a value with spaces".to_owned())
+            .unwrap();
+        let mut entry = Entry::new("Mail");
+        entry.username = "me@example.com".to_owned();
+        entry.tags.insert("work".to_owned());
+        entry.created = 0;
+        vault.put("mail".to_owned(), entry).unwrap();
+
+        let vault = Vault::open(name, "pw".to_owned()).unwrap();
+        assert_eq!(vault.tagged("work").unwrap(), ["mail"]);
+        assert!(vault.tagged("home").unwrap().is_empty());
+        let entry = vault.entry("mail").unwrap().unwrap();
+        assert_eq!(entry.username, "me@example.com");
+        assert!(entry.modified > entry.created);
+        assert_eq!(
+            vault.get("db").unwrap().as_deref(),
+            Some("a value with spaces")
+        );
     }
 }